@@ -0,0 +1,56 @@
+use std::fmt;
+
+use anchor_lang::{prelude::Pubkey, solana_program::message::CompileError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientError {
+    MissingInputMint,
+    MissingOutputMint,
+    MissingOutputAmounts,
+    /// NativeMint or NativeSol side used with a mint other than WSOL
+    NotNativeMint(Pubkey),
+    MissingFeeRecipient,
+    EmptyRoute,
+    /// Hop input mint differs from previous hop output mint
+    RouteMintMismatch(usize),
+    /// Shared account can only hold token accounts, it can't sign native SOL transfers
+    SharedAccountNativeSol(usize),
+    /// Pool legs swap between token accounts only
+    PoolNativeSol(usize),
+    /// Maker vaults hold token accounts only, receiver can't get native SOL
+    VaultNativeSol,
+    /// `Taker::SignedOrder` is only supported by `swap`
+    UnsupportedSignedOrder,
+    /// Relayer fee is only supported by `swap`
+    UnsupportedRelayerFee,
+    InvalidLookupTable(Pubkey),
+    CompileMessage(CompileError),
+    /// Instruction or account index out of transaction bounds
+    InvalidTransaction,
+    InvalidEventData,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::MissingInputMint => write!(f, "input mint is not set"),
+            ClientError::MissingOutputMint => write!(f, "output mint is not set"),
+            ClientError::MissingOutputAmounts => write!(f, "output amounts are empty"),
+            ClientError::NotNativeMint(mint) => write!(f, "native SOL account kind used with non-native mint {}", mint),
+            ClientError::MissingFeeRecipient => write!(f, "fee_bps > 0 requires fee recipient token account"),
+            ClientError::EmptyRoute => write!(f, "route has no hops"),
+            ClientError::RouteMintMismatch(hop) => write!(f, "hop {} input mint differs from previous hop output mint", hop),
+            ClientError::SharedAccountNativeSol(hop) => write!(f, "hop {} uses shared account for native SOL", hop),
+            ClientError::PoolNativeSol(hop) => write!(f, "pool hop {} can't use native SOL", hop),
+            ClientError::VaultNativeSol => write!(f, "swap from maker vault can't pay native SOL"),
+            ClientError::UnsupportedSignedOrder => write!(f, "signed taker order is only supported by swap"),
+            ClientError::UnsupportedRelayerFee => write!(f, "relayer fee is only supported by swap"),
+            ClientError::InvalidLookupTable(key) => write!(f, "account {} is not a lookup table", key),
+            ClientError::CompileMessage(err) => write!(f, "v0 message compilation failed: {}", err),
+            ClientError::InvalidTransaction => write!(f, "instruction or account index out of bounds"),
+            ClientError::InvalidEventData => write!(f, "malformed Bebop event"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
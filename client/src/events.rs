@@ -0,0 +1,242 @@
+use anchor_lang::{
+    event::EVENT_IX_TAG_LE,
+    prelude::*,
+    solana_program::instruction::CompiledInstruction,
+    Discriminator,
+};
+use bebop_rfq::{
+    bebop_rfq::{AmountWithExpiry, SharedAccountSeeds},
+    events::{BebopSwap, BebopSwapBatch, TransferKind, EVENT_VERSION},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{error::ClientError, pda::event_authority_address};
+
+const PROGRAM_DATA_LOG: &str = "Program data: ";
+
+/// Inner instructions invoked by the outer instruction at `index`, same shape as transaction status meta
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InnerInstructions {
+    pub index: u8,
+    pub instructions: Vec<CompiledInstruction>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillTaker {
+    /// Taker signed the swap
+    Wallet(Pubkey),
+    /// Taker is owner's shared-account PDA, it swapped its whole balance
+    SharedAccount(SharedAccountSeeds),
+    /// Global shared-account PDA of unversioned events
+    LegacySharedAccount,
+}
+
+/// One maker fill from a `BebopSwap` or `BebopSwapBatch` event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwapFill {
+    /// Outer instruction the fill happened in
+    pub instruction_index: u8,
+    pub event_id: u64,
+    pub taker: FillTaker,
+    pub receiver: Pubkey,
+    pub maker: Pubkey,
+    pub taker_token: Pubkey,
+    pub maker_token: Pubkey,
+    pub filled_taker_amount: u64,
+    pub filled_maker_amount: u64,
+    pub fee_amount: u64,
+    /// Quote and settlement details, only in versioned `BebopSwap` events
+    pub details: Option<SwapFillDetails>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapFillDetails {
+    pub input_amount: u64,
+    pub output_tier: u8,
+    pub output_amount: AmountWithExpiry,
+    pub partial_fill: bool,
+    pub input_transfer: TransferKind,
+    pub output_transfer: TransferKind,
+    pub positive_slippage_amount: u64,
+    pub positive_slippage_output_amount: u64,
+    pub relayer_fee_amount: u64,
+}
+
+/// `BebopSwap` logged with `emit!` before events were versioned
+#[derive(AnchorDeserialize)]
+struct LoggedBebopSwap {
+    event_id: u64,
+    maker_address: Pubkey,
+    taker_token: Pubkey,
+    maker_token: Pubkey,
+    filled_taker_amount: u64,
+    filled_maker_amount: u64,
+}
+
+/// Single `b"shared-account"` PDA was used by every taker in unversioned events
+fn legacy_taker(taker: Pubkey) -> FillTaker {
+    if taker == Pubkey::find_program_address(&[bebop_rfq::SHARED_ACCOUNT], &bebop_rfq::ID).0 {
+        FillTaker::LegacySharedAccount
+    } else {
+        FillTaker::Wallet(taker)
+    }
+}
+
+/// Decodes every Bebop fill of a transaction.
+/// `account_keys` are static keys followed by loaded writable and readonly addresses.
+/// Events are only accepted when signed by the event authority, `swap` can be invoked directly or through CPI.
+/// Events of a version newer than `EVENT_VERSION` are skipped, the rest of the transaction is still decoded
+pub fn decode_swap_fills(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+    inner_instructions: &[InnerInstructions],
+) -> std::result::Result<Vec<SwapFill>, ClientError> {
+    let event_authority = event_authority_address();
+    let key = |index: u8| account_keys.get(index as usize).copied().ok_or(ClientError::InvalidTransaction);
+
+    let mut fills = Vec::new();
+    for inner in inner_instructions {
+        let outer = instructions.get(inner.index as usize).ok_or(ClientError::InvalidTransaction)?;
+        // last Bebop instruction before the event is the one that emitted it
+        let mut swap_instruction: Option<&CompiledInstruction> = None;
+        for instruction in std::iter::once(outer).chain(inner.instructions.iter()) {
+            if key(instruction.program_id_index)? != bebop_rfq::ID {
+                continue;
+            }
+            let Some(event_data) = instruction.data.strip_prefix(&EVENT_IX_TAG_LE) else {
+                swap_instruction = Some(instruction);
+                continue;
+            };
+            if instruction.accounts.first().map(|index| key(*index)).transpose()? != Some(event_authority) {
+                continue;
+            }
+            let swap_instruction = swap_instruction.ok_or(ClientError::InvalidEventData)?;
+            let account = |position: usize| swap_instruction.accounts.get(position).map(|index| key(*index))
+                .ok_or(ClientError::InvalidTransaction)?;
+
+            if event_data.len() < 9 {
+                return Err(ClientError::InvalidEventData);
+            }
+            let (discriminator, mut payload) = event_data.split_at(8);
+            let version = payload[0];
+            if version == 0 || version > EVENT_VERSION {
+                continue;
+            }
+            if discriminator == BebopSwap::DISCRIMINATOR {
+                let event = BebopSwap::deserialize(&mut payload).map_err(|_| ClientError::InvalidEventData)?;
+                fills.push(SwapFill {
+                    instruction_index: inner.index,
+                    event_id: event.event_id,
+                    taker: match event.shared_account {
+                        Some(shared_account) => FillTaker::SharedAccount(shared_account),
+                        None => FillTaker::Wallet(event.taker),
+                    },
+                    receiver: event.receiver,
+                    maker: event.maker_address,
+                    taker_token: event.taker_token,
+                    maker_token: event.maker_token,
+                    filled_taker_amount: event.filled_taker_amount,
+                    filled_maker_amount: event.filled_maker_amount,
+                    fee_amount: event.fee_amount,
+                    details: Some(SwapFillDetails {
+                        input_amount: event.input_amount,
+                        output_tier: event.output_tier,
+                        output_amount: event.output_amount,
+                        partial_fill: event.partial_fill,
+                        input_transfer: event.input_transfer,
+                        output_transfer: event.output_transfer,
+                        positive_slippage_amount: event.positive_slippage_amount,
+                        positive_slippage_output_amount: event.positive_slippage_output_amount,
+                        relayer_fee_amount: event.relayer_fee_amount,
+                    }),
+                });
+            } else if discriminator == BebopSwapBatch::DISCRIMINATOR {
+                let event = BebopSwapBatch::deserialize(&mut payload).map_err(|_| ClientError::InvalidEventData)?;
+                // swap_batch: config, taker, receiver. Taker always signs
+                let (taker, receiver) = (FillTaker::Wallet(account(1)?), account(2)?);
+                fills.extend(event.fills.into_iter().map(|fill| SwapFill {
+                    instruction_index: inner.index,
+                    event_id: event.event_id,
+                    taker,
+                    receiver,
+                    maker: fill.maker_address,
+                    taker_token: event.taker_token,
+                    maker_token: event.maker_token,
+                    filled_taker_amount: fill.filled_taker_amount,
+                    filled_maker_amount: fill.filled_maker_amount,
+                    fee_amount: fill.fee_amount,
+                    details: None,
+                }));
+            }
+        }
+    }
+    Ok(fills)
+}
+
+/// Decodes Bebop fills of a transaction executed by the program before events were versioned,
+/// they were logged as `Program data:` by `emit!` instead of self-invoked.
+/// Invocations are followed through `log_messages`, taker and receiver come from the swap instruction accounts
+pub fn decode_logged_swap_fills(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+    inner_instructions: &[InnerInstructions],
+    log_messages: &[String],
+) -> std::result::Result<Vec<SwapFill>, ClientError> {
+    let key = |index: u8| account_keys.get(index as usize).copied().ok_or(ClientError::InvalidTransaction);
+    let bebop_program = bebop_rfq::ID.to_string();
+
+    let mut fills = Vec::new();
+    // invoked program ids, outer instruction index and count of Bebop invocations in it
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut instruction_index: Option<u8> = None;
+    let mut bebop_invocations = 0;
+    for log in log_messages {
+        if let Some((program, depth)) = log.strip_prefix("Program ").and_then(|log| log.split_once(" invoke [")) {
+            if depth == "1]" {
+                instruction_index = Some(instruction_index.map_or(0, |index| index + 1));
+                bebop_invocations = 0;
+            }
+            if program == bebop_program {
+                bebop_invocations += 1;
+            }
+            invocations.push(program);
+        } else if log.starts_with("Program ") && (log.ends_with(" success") || log.contains(" failed: ")) {
+            invocations.pop();
+        } else if let Some(data) = log.strip_prefix(PROGRAM_DATA_LOG) {
+            if invocations.last() != Some(&bebop_program.as_str()) {
+                continue;
+            }
+            let Ok(data) = STANDARD.decode(data) else {
+                continue;
+            };
+            let Some(event_data) = data.strip_prefix(&BebopSwap::DISCRIMINATOR) else {
+                continue;
+            };
+            let event = LoggedBebopSwap::try_from_slice(event_data).map_err(|_| ClientError::InvalidEventData)?;
+            let index = instruction_index.ok_or(ClientError::InvalidTransaction)?;
+            let outer = instructions.get(index as usize).ok_or(ClientError::InvalidTransaction)?;
+            let inner = inner_instructions.iter().find(|inner| inner.index == index).map_or(&[][..], |inner| &inner.instructions[..]);
+            let mut bebop_instructions = std::iter::once(outer).chain(inner.iter())
+                .filter(|instruction| key(instruction.program_id_index) == Ok(bebop_rfq::ID));
+            let swap_instruction = bebop_instructions.nth(bebop_invocations - 1).ok_or(ClientError::InvalidTransaction)?;
+            let account = |position: usize| swap_instruction.accounts.get(position).map(|index| key(*index))
+                .ok_or(ClientError::InvalidTransaction)?;
+            fills.push(SwapFill {
+                instruction_index: index,
+                event_id: event.event_id,
+                // swap: taker, maker, receiver
+                taker: legacy_taker(account(0)?),
+                receiver: account(2)?,
+                maker: event.maker_address,
+                taker_token: event.taker_token,
+                maker_token: event.maker_token,
+                filled_taker_amount: event.filled_taker_amount,
+                filled_maker_amount: event.filled_maker_amount,
+                fee_amount: 0,
+                details: None,
+            });
+        }
+    }
+    Ok(fills)
+}
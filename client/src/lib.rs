@@ -0,0 +1,23 @@
+pub mod error;
+pub mod events;
+pub mod lookup_table;
+pub mod maker_delegate;
+pub mod maker_risk_state;
+pub mod maker_vault;
+pub mod maker_wsol_vault;
+pub mod pda;
+pub mod route;
+pub mod shared_account;
+pub mod swap;
+
+pub use error::ClientError;
+pub use events::*;
+pub use lookup_table::*;
+pub use maker_delegate::*;
+pub use maker_risk_state::*;
+pub use maker_vault::*;
+pub use maker_wsol_vault::*;
+pub use pda::*;
+pub use route::*;
+pub use shared_account::*;
+pub use swap::*;
@@ -0,0 +1,92 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        address_lookup_table::{
+            instruction::{create_lookup_table, extend_lookup_table},
+            state::AddressLookupTable,
+            AddressLookupTableAccount,
+        },
+        hash::Hash,
+        instruction::Instruction,
+        message::{v0, VersionedMessage},
+        sysvar,
+    },
+    system_program,
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address_with_program_id},
+    token::{self, spl_token::native_mint},
+    token_2022,
+};
+
+use crate::{
+    error::ClientError,
+    pda::{config_address, event_authority_address, shared_account_address},
+};
+
+/// Addresses per extend instruction, keeps extend transaction under packet size
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+/// Accounts shared by every route: program ids, config, event authority and mints
+pub fn bebop_lookup_table_addresses(mints: &[(Pubkey, Pubkey)]) -> Vec<Pubkey> {
+    let mut addresses = vec![
+        bebop_rfq::ID,
+        config_address(),
+        event_authority_address(),
+        system_program::ID,
+        token::ID,
+        token_2022::ID,
+        associated_token::ID,
+        sysvar::instructions::ID,
+        native_mint::ID,
+        token_2022::spl_token_2022::native_mint::ID,
+    ];
+    for (mint, _) in mints {
+        if !addresses.contains(mint) {
+            addresses.push(*mint);
+        }
+    }
+    addresses
+}
+
+/// Owner's shared account with its token account for each (mint, token program), for a per-taker table
+pub fn shared_account_lookup_table_addresses(owner: &Pubkey, route_id: u64, mints: &[(Pubkey, Pubkey)]) -> Vec<Pubkey> {
+    let shared_account = shared_account_address(owner, route_id).0;
+    std::iter::once(shared_account)
+        .chain(mints.iter().map(|(mint, token_program)| {
+            get_associated_token_address_with_program_id(&shared_account, mint, token_program)
+        }))
+        .collect()
+}
+
+/// Create instruction and address of a lookup table, `recent_slot` must be in SlotHashes
+pub fn create_bebop_lookup_table(authority: Pubkey, payer: Pubkey, recent_slot: u64) -> (Instruction, Pubkey) {
+    create_lookup_table(authority, payer, recent_slot)
+}
+
+/// Extend instructions, one per `MAX_ADDRESSES_PER_EXTEND` addresses.
+/// New addresses are usable from the slot after the extension
+pub fn extend_bebop_lookup_table(lookup_table: Pubkey, authority: Pubkey, payer: Pubkey, addresses: &[Pubkey]) -> Vec<Instruction> {
+    addresses
+        .chunks(MAX_ADDRESSES_PER_EXTEND)
+        .map(|chunk| extend_lookup_table(lookup_table, authority, Some(payer), chunk.to_vec()))
+        .collect()
+}
+
+/// Lookup table account fetched from the chain, for v0 message compilation
+pub fn lookup_table_account(key: Pubkey, data: &[u8]) -> std::result::Result<AddressLookupTableAccount, ClientError> {
+    let lookup_table = AddressLookupTable::deserialize(data).map_err(|_| ClientError::InvalidLookupTable(key))?;
+    Ok(AddressLookupTableAccount { key, addresses: lookup_table.addresses.to_vec() })
+}
+
+/// v0 message loading every non-signer, non-program account found in `lookup_tables`
+pub fn compile_v0_message(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> std::result::Result<VersionedMessage, ClientError> {
+    v0::Message::try_compile(payer, instructions, lookup_tables, recent_blockhash)
+        .map(VersionedMessage::V0)
+        .map_err(ClientError::CompileMessage)
+}
@@ -0,0 +1,35 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::instruction::Instruction,
+    system_program, InstructionData,
+};
+use bebop_rfq::bebop_rfq::DelegateLimits;
+
+use crate::pda::maker_delegate_address;
+
+/// `set_delegate` instruction, creates the delegate or replaces its limits
+pub fn set_delegate_instruction(maker: Pubkey, delegate: Pubkey, limits: DelegateLimits) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::SetDelegate {
+            maker,
+            maker_delegate: maker_delegate_address(&maker, &delegate),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::SetDelegate { delegate, limits }.data(),
+    }
+}
+
+/// `revoke_delegate` instruction, closes the delegate account back to the maker
+pub fn revoke_delegate_instruction(maker: Pubkey, delegate: Pubkey) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::RevokeDelegate {
+            maker,
+            maker_delegate: maker_delegate_address(&maker, &delegate),
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::RevokeDelegate {}.data(),
+    }
+}
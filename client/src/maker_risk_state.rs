@@ -0,0 +1,23 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::instruction::Instruction,
+    system_program, InstructionData,
+};
+
+use crate::pda::maker_risk_state_address;
+
+/// `set_risk_limit` instruction, caps maker output of the pair within `window_slots`
+pub fn set_risk_limit_instruction(maker: Pubkey, input_mint: Pubkey, output_mint: Pubkey, window_slots: u64, max_volume: u64) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::SetRiskLimit {
+            maker,
+            maker_risk_state: maker_risk_state_address(&maker, &input_mint, &output_mint).0,
+            input_mint,
+            output_mint,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::SetRiskLimit { window_slots, max_volume }.data(),
+    }
+}
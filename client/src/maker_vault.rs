@@ -0,0 +1,56 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::instruction::Instruction,
+    system_program, InstructionData,
+};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+use crate::pda::{maker_vault_address, maker_vault_token_account_address};
+
+/// `deposit` instruction, creates maker's vault of the mint on first use
+pub fn deposit_instruction(maker: Pubkey, mint: Pubkey, token_program: Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::Deposit {
+            maker,
+            maker_vault: maker_vault_address(&maker, &mint),
+            vault_token_account: maker_vault_token_account_address(&maker, &mint),
+            maker_token_account: get_associated_token_address_with_program_id(&maker, &mint, &token_program),
+            mint,
+            token_program,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::Deposit { amount }.data(),
+    }
+}
+
+/// `withdraw` instruction, tokens go to maker's associated token account
+pub fn withdraw_instruction(maker: Pubkey, mint: Pubkey, token_program: Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::Withdraw {
+            maker,
+            maker_vault: maker_vault_address(&maker, &mint),
+            vault_token_account: maker_vault_token_account_address(&maker, &mint),
+            maker_token_account: get_associated_token_address_with_program_id(&maker, &mint, &token_program),
+            mint,
+            token_program,
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::Withdraw { amount }.data(),
+    }
+}
+
+/// `set_vault_operator` instruction, `operator` signs `swap_from_vault` paying out of this vault
+pub fn set_vault_operator_instruction(maker: Pubkey, mint: Pubkey, operator: Pubkey) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::SetVaultOperator {
+            maker,
+            maker_vault: maker_vault_address(&maker, &mint),
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::SetVaultOperator { operator }.data(),
+    }
+}
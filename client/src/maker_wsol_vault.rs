@@ -0,0 +1,41 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::instruction::Instruction,
+    system_program, InstructionData,
+};
+use anchor_spl::{associated_token::get_associated_token_address, token::spl_token::{self, native_mint}};
+
+use crate::pda::{maker_wsol_vault_address, maker_wsol_vault_token_account_address};
+
+/// `init_maker_wsol_vault` instruction, idempotent: also tops up vault lamports
+pub fn init_maker_wsol_vault_instruction(maker: Pubkey, lamports: u64) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::InitMakerWsolVault {
+            maker,
+            maker_wsol_vault: maker_wsol_vault_address(&maker),
+            vault_token_account: maker_wsol_vault_token_account_address(&maker),
+            wsol_mint: native_mint::ID,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::InitMakerWsolVault { lamports }.data(),
+    }
+}
+
+/// `withdraw_maker_wsol_vault` instruction, WSOL goes to maker's associated WSOL token account
+pub fn withdraw_maker_wsol_vault_instruction(maker: Pubkey, lamports: u64, wsol_amount: u64) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::WithdrawMakerWsolVault {
+            maker,
+            maker_wsol_vault: maker_wsol_vault_address(&maker),
+            vault_token_account: maker_wsol_vault_token_account_address(&maker),
+            maker_wsol_token_account: get_associated_token_address(&maker, &native_mint::ID),
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::WithdrawMakerWsolVault { lamports, wsol_amount }.data(),
+    }
+}
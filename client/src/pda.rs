@@ -0,0 +1,100 @@
+use anchor_lang::prelude::Pubkey;
+use bebop_rfq::{bebop_rfq::{PdaBumps, SharedAccountSeeds}, state::NonceBitmap};
+
+pub fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[bebop_rfq::CONFIG], &bebop_rfq::ID).0
+}
+
+/// Signs `emit_cpi!` self-invocations carrying Bebop events
+pub fn event_authority_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &bebop_rfq::ID).0
+}
+
+/// Owner's PDA holding intermediate outputs of a route, `route_id` isolates concurrent routes of one owner
+pub fn shared_account_address(owner: &Pubkey, route_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[bebop_rfq::SHARED_ACCOUNT, owner.as_ref(), &route_id.to_le_bytes()],
+        &bebop_rfq::ID,
+    )
+}
+
+/// Maker's PDA used to unwrap WSOL into native SOL
+pub fn temporary_wsol_token_account_address(maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[bebop_rfq::TEMPORARY_WSOL_TOKEN_ACCOUNT, maker.as_ref()], &bebop_rfq::ID)
+}
+
+/// Token delegate approved by makers for fills they don't sign (`swap_signed`, delegated `swap`)
+pub fn maker_authority_address() -> Pubkey {
+    Pubkey::find_program_address(&[bebop_rfq::MAKER_AUTHORITY], &bebop_rfq::ID).0
+}
+
+/// Token delegate approved by gasless takers signing `TakerOrder`
+pub fn taker_authority_address() -> Pubkey {
+    Pubkey::find_program_address(&[bebop_rfq::TAKER_AUTHORITY], &bebop_rfq::ID).0
+}
+
+/// Maker's scoped authorization of a hot key signing `swap` for it
+pub fn maker_delegate_address(maker: &Pubkey, delegate: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[bebop_rfq::MAKER_DELEGATE, maker.as_ref(), delegate.as_ref()], &bebop_rfq::ID).0
+}
+
+/// Maker's rolling volume cap of a mint pair
+pub fn maker_risk_state_address(maker: &Pubkey, input_mint: &Pubkey, output_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[bebop_rfq::MAKER_RISK_STATE, maker.as_ref(), input_mint.as_ref(), output_mint.as_ref()],
+        &bebop_rfq::ID,
+    )
+}
+
+/// Maker's persistent WSOL vault, replaces the temporary account on unwrap paths once initialized
+pub fn maker_wsol_vault_address(maker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[bebop_rfq::MAKER_WSOL_VAULT, maker.as_ref()], &bebop_rfq::ID).0
+}
+
+pub fn maker_wsol_vault_token_account_address(maker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[bebop_rfq::MAKER_WSOL_VAULT, maker_wsol_vault_address(maker).as_ref()],
+        &bebop_rfq::ID,
+    ).0
+}
+
+/// Maker's escrow vault of `mint`, pays out `swap_from_vault` without maker signature
+pub fn maker_vault_address(maker: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[bebop_rfq::MAKER_VAULT, maker.as_ref(), mint.as_ref()], &bebop_rfq::ID).0
+}
+
+pub fn maker_vault_token_account_address(maker: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[bebop_rfq::MAKER_VAULT, maker_vault_address(maker, mint).as_ref()],
+        &bebop_rfq::ID,
+    ).0
+}
+
+pub fn nonce_bitmap_address(maker: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[bebop_rfq::NONCE_BITMAP, maker.as_ref(), &NonceBitmap::word_pos(nonce).to_le_bytes()],
+        &bebop_rfq::ID,
+    ).0
+}
+
+/// Taker's bitmap of `TakerOrder::taker_nonce`
+pub fn taker_nonce_bitmap_address(taker: &Pubkey, taker_nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[bebop_rfq::TAKER_NONCE_BITMAP, taker.as_ref(), &NonceBitmap::word_pos(taker_nonce).to_le_bytes()],
+        &bebop_rfq::ID,
+    ).0
+}
+
+/// `shared_account` is only needed when taker is shared-account PDA
+pub fn pda_bumps(
+    maker: &Pubkey,
+    input_mint: &Pubkey,
+    output_mint: &Pubkey,
+    shared_account: Option<&SharedAccountSeeds>,
+) -> PdaBumps {
+    PdaBumps {
+        shared_account: shared_account.map_or(0, |seeds| shared_account_address(&seeds.owner, seeds.route_id).1),
+        temporary_wsol_token_account: temporary_wsol_token_account_address(maker).1,
+        maker_risk_state: maker_risk_state_address(maker, input_mint, output_mint).1,
+    }
+}
@@ -0,0 +1,293 @@
+use anchor_lang::{prelude::*, solana_program::instruction::Instruction};
+use anchor_spl::associated_token::{
+    get_associated_token_address_with_program_id,
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent,
+};
+use bebop_rfq::bebop_rfq::{
+    AmountWithExpiry, PositiveSlippage, SharedAccountSeeds, TransferFeeMode,
+};
+
+use crate::{
+    error::ClientError,
+    pda::shared_account_address,
+    shared_account::sweep_shared_instruction,
+    swap::{is_native_mint, AccountKind, SwapBuilder, Taker},
+};
+
+/// RFQ maker leg, settled by `swap`
+#[derive(Clone, Default)]
+pub struct RfqHop {
+    pub maker: Pubkey,
+    pub input_mint: Pubkey,
+    pub input_token_program: Pubkey,
+    pub maker_input: AccountKind,
+    pub output_mint: Pubkey,
+    pub output_token_program: Pubkey,
+    pub maker_output: AccountKind,
+    /// Ignored when taker is shared account, its whole balance is swapped
+    pub input_amount: u64,
+    pub output_amounts: Vec<AmountWithExpiry>,
+    pub nonce: u64,
+    /// fee_bps and fee recipient token account
+    pub fee: Option<(u16, Pubkey)>,
+    pub transfer_fee_mode: TransferFeeMode,
+    pub transfer_hook_accounts: Vec<AccountMeta>,
+    /// See `SwapBuilder::surplus_to_receiver`, receiver's input token account is created by the route
+    pub surplus_to_receiver: bool,
+    /// See `SwapBuilder::positive_slippage`
+    pub positive_slippage: Option<PositiveSlippage>,
+    /// See `SwapBuilder::maker_wsol_vault`
+    pub maker_wsol_vault: bool,
+}
+
+/// Token accounts a pool leg swaps between, both owned by `owner`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolAccounts {
+    pub owner: Pubkey,
+    pub input_token_account: Pubkey,
+    pub output_token_account: Pubkey,
+}
+
+/// External pool leg, signed by the taker from its own token account
+pub struct PoolHop {
+    pub input_mint: Pubkey,
+    pub input_token_program: Pubkey,
+    pub output_mint: Pubkey,
+    pub output_token_program: Pubkey,
+    pub instruction: Box<dyn Fn(&PoolAccounts) -> Instruction>,
+}
+
+pub enum Hop {
+    Rfq(RfqHop),
+    Pool(PoolHop),
+}
+
+impl Hop {
+    fn input(&self) -> (Pubkey, Pubkey) {
+        match self {
+            Hop::Rfq(hop) => (hop.input_mint, hop.input_token_program),
+            Hop::Pool(hop) => (hop.input_mint, hop.input_token_program),
+        }
+    }
+
+    fn output(&self) -> (Pubkey, Pubkey) {
+        match self {
+            Hop::Rfq(hop) => (hop.output_mint, hop.output_token_program),
+            Hop::Pool(hop) => (hop.output_mint, hop.output_token_program),
+        }
+    }
+}
+
+/// Ordered hops from taker to receiver.
+/// Output of a hop followed by an RFQ leg goes to taker's shared account, which then swaps its whole balance.
+/// Output of a hop followed by a pool leg goes to the taker, pool legs can't spend from the shared account.
+pub struct Route {
+    taker: Pubkey,
+    payer: Pubkey,
+    receiver: Option<Pubkey>,
+    taker_input: AccountKind,
+    receiver_output: AccountKind,
+    event_id: u64,
+    route_id: u64,
+    close_shared_accounts: bool,
+    hops: Vec<Hop>,
+}
+
+impl Route {
+    pub fn new(taker: Pubkey, payer: Pubkey) -> Self {
+        Self {
+            taker,
+            payer,
+            receiver: None,
+            taker_input: AccountKind::Token,
+            receiver_output: AccountKind::Token,
+            event_id: 0,
+            route_id: 0,
+            close_shared_accounts: false,
+            hops: Vec::new(),
+        }
+    }
+
+    /// Defaults to taker address
+    pub fn receiver(mut self, receiver: Pubkey) -> Self {
+        self.receiver = Some(receiver);
+        self
+    }
+
+    /// How taker holds the route input
+    pub fn taker_input(mut self, kind: AccountKind) -> Self {
+        self.taker_input = kind;
+        self
+    }
+
+    /// How receiver gets the route output
+    pub fn receiver_output(mut self, kind: AccountKind) -> Self {
+        self.receiver_output = kind;
+        self
+    }
+
+    /// Same event_id for every RFQ leg
+    pub fn event_id(mut self, event_id: u64) -> Self {
+        self.event_id = event_id;
+        self
+    }
+
+    /// Selects taker's shared account, concurrent routes of one taker need distinct ids
+    pub fn route_id(mut self, route_id: u64) -> Self {
+        self.route_id = route_id;
+        self
+    }
+
+    /// Appends `sweep_shared` for every shared account token account the route uses,
+    /// sweeping leftovers and closing the account back to the taker
+    pub fn close_shared_accounts(mut self, close_shared_accounts: bool) -> Self {
+        self.close_shared_accounts = close_shared_accounts;
+        self
+    }
+
+    pub fn hop(mut self, hop: Hop) -> Self {
+        self.hops.push(hop);
+        self
+    }
+
+    /// Associated token account creations followed by every hop instruction
+    pub fn instructions(&self) -> std::result::Result<Vec<Instruction>, ClientError> {
+        if self.hops.is_empty() {
+            return Err(ClientError::EmptyRoute);
+        }
+        let shared_account_seeds = SharedAccountSeeds {
+            owner: self.taker,
+            route_id: self.route_id,
+        };
+        let shared_account = shared_account_address(&self.taker, self.route_id).0;
+        let receiver = self.receiver.unwrap_or(self.taker);
+        let last = self.hops.len() - 1;
+
+        let mut ata_instructions: Vec<Instruction> = Vec::new();
+        let mut create_ata = |owner: &Pubkey, (mint, token_program): (Pubkey, Pubkey)| {
+            let ix = create_associated_token_account_idempotent(
+                &self.payer,
+                owner,
+                &mint,
+                &token_program,
+            );
+            if !ata_instructions.contains(&ix) {
+                ata_instructions.push(ix);
+            }
+        };
+
+        let mut hop_instructions: Vec<Instruction> = Vec::new();
+        let mut shared_tokens: Vec<(Pubkey, Pubkey)> = Vec::new();
+        // holder of the current hop input and how it holds it
+        let mut holder = Taker::Wallet(self.taker);
+        let mut holder_kind = self.taker_input;
+        for (i, hop) in self.hops.iter().enumerate() {
+            if i > 0 && hop.input().0 != self.hops[i - 1].output().0 {
+                return Err(ClientError::RouteMintMismatch(i));
+            }
+            let (hop_receiver, receiver_kind) = if i == last {
+                (receiver, self.receiver_output)
+            } else if matches!(self.hops[i + 1], Hop::Rfq(_)) {
+                (shared_account, token_kind(&hop.output().0))
+            } else {
+                (self.taker, token_kind(&hop.output().0))
+            };
+            if hop_receiver == shared_account && receiver_kind == AccountKind::NativeSol {
+                return Err(ClientError::SharedAccountNativeSol(i));
+            }
+            if receiver_kind != AccountKind::NativeSol {
+                create_ata(&hop_receiver, hop.output());
+            }
+            if hop_receiver == shared_account && !shared_tokens.contains(&hop.output()) {
+                shared_tokens.push(hop.output());
+            }
+
+            match hop {
+                Hop::Rfq(hop) => {
+                    if matches!(holder, Taker::SharedAccount(_))
+                        && hop.maker_input == AccountKind::NativeSol
+                    {
+                        return Err(ClientError::SharedAccountNativeSol(i));
+                    }
+                    if hop_receiver == shared_account && hop.maker_output == AccountKind::NativeSol
+                    {
+                        return Err(ClientError::SharedAccountNativeSol(i));
+                    }
+                    if hop.surplus_to_receiver {
+                        create_ata(&hop_receiver, (hop.input_mint, hop.input_token_program));
+                    }
+                    let mut builder = SwapBuilder::new(holder, hop.maker, self.payer)
+                        .receiver(hop_receiver)
+                        .input_mint(
+                            hop.input_mint,
+                            hop.input_token_program,
+                            holder_kind,
+                            hop.maker_input,
+                        )
+                        .output_mint(
+                            hop.output_mint,
+                            hop.output_token_program,
+                            hop.maker_output,
+                            receiver_kind,
+                        )
+                        .amounts(hop.input_amount, hop.output_amounts.clone())
+                        .event_id(self.event_id)
+                        .nonce(hop.nonce)
+                        .transfer_fee_mode(hop.transfer_fee_mode)
+                        .transfer_hook_accounts(hop.transfer_hook_accounts.clone())
+                        .surplus_to_receiver(hop.surplus_to_receiver)
+                        .maker_wsol_vault(hop.maker_wsol_vault);
+                    if let Some((fee_bps, fee_recipient_token_account)) = hop.fee {
+                        builder = builder.fee(fee_bps, fee_recipient_token_account);
+                    }
+                    if let Some(positive_slippage) = hop.positive_slippage {
+                        builder = builder.positive_slippage(positive_slippage);
+                    }
+                    hop_instructions.push(builder.build()?);
+                }
+                Hop::Pool(hop) => {
+                    if holder_kind == AccountKind::NativeSol
+                        || receiver_kind == AccountKind::NativeSol
+                    {
+                        return Err(ClientError::PoolNativeSol(i));
+                    }
+                    let owner = holder.address();
+                    hop_instructions.push((hop.instruction)(&PoolAccounts {
+                        owner,
+                        input_token_account: get_associated_token_address_with_program_id(
+                            &owner,
+                            &hop.input_mint,
+                            &hop.input_token_program,
+                        ),
+                        output_token_account: get_associated_token_address_with_program_id(
+                            &hop_receiver,
+                            &hop.output_mint,
+                            &hop.output_token_program,
+                        ),
+                    }));
+                }
+            }
+            holder = if hop_receiver == shared_account {
+                Taker::SharedAccount(shared_account_seeds)
+            } else {
+                Taker::Wallet(hop_receiver)
+            };
+            holder_kind = receiver_kind;
+        }
+        ata_instructions.extend(hop_instructions);
+        if self.close_shared_accounts {
+            ata_instructions.extend(shared_tokens.into_iter().map(|token| {
+                sweep_shared_instruction(self.taker, self.route_id, Some(token), true)
+            }));
+        }
+        Ok(ata_instructions)
+    }
+}
+
+fn token_kind(mint: &Pubkey) -> AccountKind {
+    if is_native_mint(mint) {
+        AccountKind::NativeMint
+    } else {
+        AccountKind::Token
+    }
+}
@@ -0,0 +1,44 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::instruction::Instruction,
+    system_program, InstructionData,
+};
+use anchor_spl::associated_token::{
+    get_associated_token_address_with_program_id, spl_associated_token_account::instruction::create_associated_token_account_idempotent,
+};
+
+use crate::pda::shared_account_address;
+
+/// Idempotent creation of owner's shared account token account, paid by `payer`
+pub fn create_shared_token_account_instruction(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    route_id: u64,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    create_associated_token_account_idempotent(payer, &shared_account_address(owner, route_id).0, mint, token_program)
+}
+
+/// `sweep_shared` instruction moving lamports above rent of taker's shared account to `taker`,
+/// and with `token` (mint, token program) its whole token balance to taker's associated token account.
+/// `close_token_account` also closes the shared account token account, rent goes to `taker`
+pub fn sweep_shared_instruction(taker: Pubkey, route_id: u64, token: Option<(Pubkey, Pubkey)>, close_token_account: bool) -> Instruction {
+    let (shared_account, shared_account_bump) = shared_account_address(&taker, route_id);
+    let token_account = |owner: &Pubkey| token
+        .map(|(mint, token_program)| get_associated_token_address_with_program_id(owner, &mint, &token_program));
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::SweepShared {
+            taker,
+            shared_account,
+            shared_token_account: token_account(&shared_account),
+            taker_token_account: token_account(&taker),
+            mint: token.map(|(mint, _)| mint),
+            token_program: token.map(|(_, token_program)| token_program),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::SweepShared { route_id, shared_account_bump, close_token_account }.data(),
+    }
+}
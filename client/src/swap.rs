@@ -0,0 +1,425 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::instruction::Instruction,
+    solana_program::sysvar::instructions as instructions_sysvar,
+    system_program, InstructionData,
+};
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token::spl_token::native_mint,
+    token_2022::spl_token_2022,
+};
+use bebop_rfq::bebop_rfq::{AmountWithExpiry, PositiveSlippage, RelayerFee, SharedAccountSeeds, TakerOrder, TransferFeeMode};
+
+use crate::{
+    error::ClientError,
+    pda::{
+        config_address, event_authority_address, maker_authority_address, maker_delegate_address, maker_risk_state_address, maker_vault_address,
+        maker_vault_token_account_address, maker_wsol_vault_address, maker_wsol_vault_token_account_address, nonce_bitmap_address,
+        pda_bumps, shared_account_address, taker_authority_address, taker_nonce_bitmap_address, temporary_wsol_token_account_address,
+    },
+};
+
+/// How one side of the swap holds its asset
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccountKind {
+    /// Associated token account of the mint
+    #[default]
+    Token,
+    /// Associated WSOL token account
+    NativeMint,
+    /// Plain lamports on the wallet, no token account
+    NativeSol,
+}
+
+/// Wrapped SOL mint of spl-token or Token-2022
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+impl AccountKind {
+    fn is_native(&self) -> bool {
+        *self != AccountKind::Token
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Taker {
+    /// Taker signs the swap instruction
+    Wallet(Pubkey),
+    /// Taker is owner's `shared-account` PDA, e.g. output of a previous hop. The owner signs the swap instruction
+    SharedAccount(SharedAccountSeeds),
+    /// Gasless taker doesn't sign the transaction, it signs `SwapBuilder::taker_order` with Ed25519 instead.
+    /// Its input token account must approve `taker-authority` PDA
+    SignedOrder(Pubkey),
+}
+
+impl Taker {
+    pub fn address(&self) -> Pubkey {
+        match self {
+            Taker::Wallet(address) | Taker::SignedOrder(address) => *address,
+            Taker::SharedAccount(seeds) => shared_account_address(&seeds.owner, seeds.route_id).0,
+        }
+    }
+
+    fn shared_account(&self) -> Option<SharedAccountSeeds> {
+        match self {
+            Taker::Wallet(_) | Taker::SignedOrder(_) => None,
+            Taker::SharedAccount(seeds) => Some(*seeds),
+        }
+    }
+}
+
+/// Builds `swap` instruction, deriving token accounts and PDAs from account kinds
+#[derive(Clone)]
+pub struct SwapBuilder {
+    taker: Taker,
+    maker: Pubkey,
+    payer: Pubkey,
+    receiver: Option<Pubkey>,
+    input_mint: Option<(Pubkey, Pubkey)>,
+    output_mint: Option<(Pubkey, Pubkey)>,
+    taker_input: AccountKind,
+    maker_input: AccountKind,
+    maker_output: AccountKind,
+    receiver_output: AccountKind,
+    input_amount: u64,
+    output_amounts: Vec<AmountWithExpiry>,
+    event_id: u64,
+    fee_bps: u16,
+    fee_recipient_token_account: Option<Pubkey>,
+    nonce: u64,
+    transfer_fee_mode: TransferFeeMode,
+    transfer_hook_accounts: Vec<AccountMeta>,
+    surplus_to_receiver: bool,
+    positive_slippage: Option<PositiveSlippage>,
+    maker_wsol_vault: bool,
+    delegate: Option<Pubkey>,
+    relayer_fee: Option<RelayerFee>,
+    taker_nonce: u64,
+}
+
+impl SwapBuilder {
+    pub fn new(taker: Taker, maker: Pubkey, payer: Pubkey) -> Self {
+        Self {
+            taker,
+            maker,
+            payer,
+            receiver: None,
+            input_mint: None,
+            output_mint: None,
+            taker_input: AccountKind::Token,
+            maker_input: AccountKind::Token,
+            maker_output: AccountKind::Token,
+            receiver_output: AccountKind::Token,
+            input_amount: 0,
+            output_amounts: Vec::new(),
+            event_id: 0,
+            fee_bps: 0,
+            fee_recipient_token_account: None,
+            nonce: 0,
+            transfer_fee_mode: TransferFeeMode::default(),
+            transfer_hook_accounts: Vec::new(),
+            surplus_to_receiver: false,
+            positive_slippage: None,
+            maker_wsol_vault: false,
+            delegate: None,
+            relayer_fee: None,
+            taker_nonce: 0,
+        }
+    }
+
+    /// Defaults to taker address
+    pub fn receiver(mut self, receiver: Pubkey) -> Self {
+        self.receiver = Some(receiver);
+        self
+    }
+
+    pub fn input_mint(mut self, mint: Pubkey, token_program: Pubkey, taker_kind: AccountKind, maker_kind: AccountKind) -> Self {
+        self.input_mint = Some((mint, token_program));
+        self.taker_input = taker_kind;
+        self.maker_input = maker_kind;
+        self
+    }
+
+    pub fn output_mint(mut self, mint: Pubkey, token_program: Pubkey, maker_kind: AccountKind, receiver_kind: AccountKind) -> Self {
+        self.output_mint = Some((mint, token_program));
+        self.maker_output = maker_kind;
+        self.receiver_output = receiver_kind;
+        self
+    }
+
+    pub fn amounts(mut self, input_amount: u64, output_amounts: Vec<AmountWithExpiry>) -> Self {
+        self.input_amount = input_amount;
+        self.output_amounts = output_amounts;
+        self
+    }
+
+    pub fn event_id(mut self, event_id: u64) -> Self {
+        self.event_id = event_id;
+        self
+    }
+
+    pub fn fee(mut self, fee_bps: u16, fee_recipient_token_account: Pubkey) -> Self {
+        self.fee_bps = fee_bps;
+        self.fee_recipient_token_account = Some(fee_recipient_token_account);
+        self
+    }
+
+    pub fn nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// Nonce of `Taker::SignedOrder` taker, spent in taker's own nonce bitmap
+    pub fn taker_nonce(mut self, taker_nonce: u64) -> Self {
+        self.taker_nonce = taker_nonce;
+        self
+    }
+
+    pub fn transfer_fee_mode(mut self, transfer_fee_mode: TransferFeeMode) -> Self {
+        self.transfer_fee_mode = transfer_fee_mode;
+        self
+    }
+
+    /// Transfer-hook program and extra accounts, appended to remaining accounts
+    pub fn transfer_hook_accounts(mut self, accounts: Vec<AccountMeta>) -> Self {
+        self.transfer_hook_accounts = accounts;
+        self
+    }
+
+    /// Shared-account taker pays the maker at most `input_amount`, the rest of its balance
+    /// goes to receiver's input mint associated token account, which must exist
+    pub fn surplus_to_receiver(mut self, surplus_to_receiver: bool) -> Self {
+        self.surplus_to_receiver = surplus_to_receiver;
+        self
+    }
+
+    /// Shared-account taker's balance above `input_amount` is split by the policy,
+    /// by default all of it goes to the maker
+    pub fn positive_slippage(mut self, positive_slippage: PositiveSlippage) -> Self {
+        self.positive_slippage = Some(positive_slippage);
+        self
+    }
+
+    /// Unwrap through maker's persistent WSOL vault instead of the temporary WSOL PDA,
+    /// the vault must be initialized and hold enough lamports
+    pub fn maker_wsol_vault(mut self, maker_wsol_vault: bool) -> Self {
+        self.maker_wsol_vault = maker_wsol_vault;
+        self
+    }
+
+    /// Maker's hot key signs instead of the maker, maker output moves through `maker-authority` delegation
+    pub fn delegate(mut self, delegate: Pubkey) -> Self {
+        self.delegate = Some(delegate);
+        self
+    }
+
+    /// Reimburses the payer from the receiver's output, into payer's output mint associated token account
+    /// or in lamports for native SOL maker output. `max_amount` goes into `taker_order`
+    pub fn relayer_fee(mut self, relayer_fee: RelayerFee) -> Self {
+        self.relayer_fee = Some(relayer_fee);
+        self
+    }
+
+    /// Quote the `Taker::SignedOrder` taker signs, the signature goes into an Ed25519 instruction right before the swap
+    pub fn taker_order(&self) -> std::result::Result<TakerOrder, ClientError> {
+        let (input_mint, _) = self.input_mint.ok_or(ClientError::MissingInputMint)?;
+        let (output_mint, _) = self.output_mint.ok_or(ClientError::MissingOutputMint)?;
+        let taker = self.taker.address();
+        Ok(TakerOrder {
+            taker,
+            receiver: self.receiver.unwrap_or(taker),
+            maker: self.maker,
+            input_mint,
+            output_mint,
+            input_amount: self.input_amount,
+            output_amounts: self.output_amounts.clone(),
+            nonce: self.nonce,
+            fee_bps: self.fee_bps,
+            fee_recipient_token_account: self.fee_recipient_token_account.filter(|_| self.fee_bps > 0).unwrap_or_default(),
+            transfer_fee_mode: self.transfer_fee_mode,
+            max_relayer_fee: self.relayer_fee.map_or(0, |relayer_fee| relayer_fee.max_amount),
+            relayer: self.relayer_fee.map_or(Pubkey::default(), |_| self.payer),
+            taker_nonce: self.taker_nonce,
+        })
+    }
+
+    /// Maker's temporary WSOL PDA is needed when one side unwraps WSOL into native SOL
+    pub fn needs_temporary_wsol_token_account(&self) -> bool {
+        (self.taker_input != AccountKind::NativeSol && self.maker_input == AccountKind::NativeSol)
+            || (self.maker_output != AccountKind::NativeSol && self.receiver_output == AccountKind::NativeSol)
+    }
+
+    pub fn build(&self) -> std::result::Result<Instruction, ClientError> {
+        let (input_mint, input_token_program) = self.input_mint.ok_or(ClientError::MissingInputMint)?;
+        let (output_mint, output_token_program) = self.output_mint.ok_or(ClientError::MissingOutputMint)?;
+        if self.output_amounts.is_empty() {
+            return Err(ClientError::MissingOutputAmounts);
+        }
+        if (self.taker_input.is_native() || self.maker_input.is_native()) && !is_native_mint(&input_mint) {
+            return Err(ClientError::NotNativeMint(input_mint));
+        }
+        if (self.maker_output.is_native() || self.receiver_output.is_native()) && !is_native_mint(&output_mint) {
+            return Err(ClientError::NotNativeMint(output_mint));
+        }
+        if self.fee_bps > 0 && self.fee_recipient_token_account.is_none() {
+            return Err(ClientError::MissingFeeRecipient);
+        }
+
+        let taker = self.taker.address();
+        let receiver = self.receiver.unwrap_or(taker);
+        let token_account = |wallet: &Pubkey, kind: AccountKind, mint: &Pubkey, token_program: &Pubkey| match kind {
+            AccountKind::NativeSol => None,
+            _ => Some(get_associated_token_address_with_program_id(wallet, mint, token_program)),
+        };
+
+        let accounts = bebop_rfq::accounts::Swap {
+            config: config_address(),
+            taker,
+            maker: self.maker,
+            receiver,
+            taker_input_mint_token_account: token_account(&taker, self.taker_input, &input_mint, &input_token_program),
+            maker_input_mint_token_account: token_account(&self.maker, self.maker_input, &input_mint, &input_token_program),
+            receiver_output_mint_token_account: token_account(&receiver, self.receiver_output, &output_mint, &output_token_program),
+            maker_output_mint_token_account: token_account(&self.maker, self.maker_output, &output_mint, &output_token_program),
+            fee_recipient_token_account: if self.fee_bps > 0 { self.fee_recipient_token_account } else { None },
+            receiver_input_mint_token_account: self.surplus_to_receiver
+                .then(|| get_associated_token_address_with_program_id(&receiver, &input_mint, &input_token_program)),
+            nonce_bitmap: nonce_bitmap_address(&self.maker, self.nonce),
+            input_mint,
+            input_token_program,
+            output_mint,
+            output_token_program,
+            payer: self.payer,
+            system_program: system_program::ID,
+            delegate: self.delegate,
+            maker_delegate: self.delegate.map(|delegate| maker_delegate_address(&self.maker, &delegate)),
+            maker_authority: self.delegate.map(|_| maker_authority_address()),
+            maker_risk_state: maker_risk_state_address(&self.maker, &input_mint, &output_mint).0,
+            instructions_sysvar: matches!(self.taker, Taker::SignedOrder(_)).then_some(instructions_sysvar::ID),
+            taker_authority: matches!(self.taker, Taker::SignedOrder(_)).then(taker_authority_address),
+            taker_nonce_bitmap: matches!(self.taker, Taker::SignedOrder(_)).then(|| taker_nonce_bitmap_address(&taker, self.taker_nonce)),
+            relayer_fee_token_account: self.relayer_fee
+                .and(token_account(&self.payer, self.maker_output, &output_mint, &output_token_program)),
+            shared_account_owner: self.taker.shared_account().map(|seeds| seeds.owner),
+            event_authority: event_authority_address(),
+            program: bebop_rfq::ID,
+        };
+        let mut accounts = accounts.to_account_metas(None);
+        if let Taker::Wallet(_) = self.taker {
+            accounts
+                .iter_mut()
+                .for_each(|account| if account.pubkey == taker { account.is_signer = true });
+        }
+        if self.delegate.is_none() {
+            accounts
+                .iter_mut()
+                .for_each(|account| if account.pubkey == self.maker { account.is_signer = true });
+        }
+        if self.needs_temporary_wsol_token_account() && self.maker_wsol_vault {
+            accounts.push(AccountMeta::new(maker_wsol_vault_address(&self.maker), false));
+            accounts.push(AccountMeta::new(maker_wsol_vault_token_account_address(&self.maker), false));
+        } else if self.needs_temporary_wsol_token_account() {
+            accounts.push(AccountMeta::new(temporary_wsol_token_account_address(&self.maker).0, false));
+        }
+        accounts.extend(self.transfer_hook_accounts.iter().cloned());
+
+        Ok(Instruction {
+            program_id: bebop_rfq::ID,
+            accounts,
+            data: bebop_rfq::instruction::Swap {
+                input_amount: self.input_amount,
+                output_amounts: self.output_amounts.clone(),
+                event_id: self.event_id,
+                fee_bps: self.fee_bps,
+                nonce: self.nonce,
+                transfer_fee_mode: self.transfer_fee_mode,
+                bumps: pda_bumps(&self.maker, &input_mint, &output_mint, self.taker.shared_account().as_ref()),
+                shared_account: self.taker.shared_account(),
+                positive_slippage: self.positive_slippage,
+                relayer_fee: self.relayer_fee,
+                taker_nonce: matches!(self.taker, Taker::SignedOrder(_)).then_some(self.taker_nonce),
+            }
+            .data(),
+        })
+    }
+
+    /// Builds `swap_from_vault`: maker side settles from maker's vaults, signed by vault `operator`.
+    /// Maker account kinds and `maker_wsol_vault` are ignored
+    pub fn build_from_vault(&self, operator: Pubkey) -> std::result::Result<Instruction, ClientError> {
+        let (input_mint, input_token_program) = self.input_mint.ok_or(ClientError::MissingInputMint)?;
+        let (output_mint, output_token_program) = self.output_mint.ok_or(ClientError::MissingOutputMint)?;
+        if self.output_amounts.is_empty() {
+            return Err(ClientError::MissingOutputAmounts);
+        }
+        if self.taker_input.is_native() && !is_native_mint(&input_mint) {
+            return Err(ClientError::NotNativeMint(input_mint));
+        }
+        if self.receiver_output == AccountKind::NativeSol {
+            return Err(ClientError::VaultNativeSol);
+        }
+        if let Taker::SignedOrder(_) = self.taker {
+            return Err(ClientError::UnsupportedSignedOrder);
+        }
+        if self.relayer_fee.is_some() {
+            return Err(ClientError::UnsupportedRelayerFee);
+        }
+        if self.fee_bps > 0 && self.fee_recipient_token_account.is_none() {
+            return Err(ClientError::MissingFeeRecipient);
+        }
+
+        let taker = self.taker.address();
+        let receiver = self.receiver.unwrap_or(taker);
+        let accounts = bebop_rfq::accounts::SwapFromVault {
+            config: config_address(),
+            taker,
+            maker: self.maker,
+            operator,
+            receiver,
+            maker_input_vault: maker_vault_address(&self.maker, &input_mint),
+            maker_output_vault: maker_vault_address(&self.maker, &output_mint),
+            taker_input_mint_token_account: (self.taker_input != AccountKind::NativeSol)
+                .then(|| get_associated_token_address_with_program_id(&taker, &input_mint, &input_token_program)),
+            maker_input_vault_token_account: maker_vault_token_account_address(&self.maker, &input_mint),
+            receiver_output_mint_token_account: get_associated_token_address_with_program_id(&receiver, &output_mint, &output_token_program),
+            maker_output_vault_token_account: maker_vault_token_account_address(&self.maker, &output_mint),
+            fee_recipient_token_account: if self.fee_bps > 0 { self.fee_recipient_token_account } else { None },
+            nonce_bitmap: nonce_bitmap_address(&self.maker, self.nonce),
+            maker_risk_state: maker_risk_state_address(&self.maker, &input_mint, &output_mint).0,
+            input_mint,
+            input_token_program,
+            output_mint,
+            output_token_program,
+            payer: self.payer,
+            system_program: system_program::ID,
+            shared_account_owner: self.taker.shared_account().map(|seeds| seeds.owner),
+            event_authority: event_authority_address(),
+            program: bebop_rfq::ID,
+        };
+        let mut accounts = accounts.to_account_metas(None);
+        if let Taker::Wallet(_) = self.taker {
+            accounts
+                .iter_mut()
+                .for_each(|account| if account.pubkey == taker { account.is_signer = true });
+        }
+        accounts.extend(self.transfer_hook_accounts.iter().cloned());
+
+        Ok(Instruction {
+            program_id: bebop_rfq::ID,
+            accounts,
+            data: bebop_rfq::instruction::SwapFromVault {
+                input_amount: self.input_amount,
+                output_amounts: self.output_amounts.clone(),
+                event_id: self.event_id,
+                fee_bps: self.fee_bps,
+                nonce: self.nonce,
+                transfer_fee_mode: self.transfer_fee_mode,
+                bumps: pda_bumps(&self.maker, &input_mint, &output_mint, self.taker.shared_account().as_ref()),
+                shared_account: self.taker.shared_account(),
+                positive_slippage: self.positive_slippage,
+            }
+            .data(),
+        })
+    }
+}
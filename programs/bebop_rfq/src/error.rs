@@ -9,5 +9,43 @@ pub enum BebopError {
     Token2022MintExtensionNotSupported,
     OrderExpired,
     InvalidNativeTokenAddress,
-    InvalidOutputAmount
+    InvalidOutputAmount,
+    InvalidFeeBps,
+    MissingFeeRecipient,
+    ProgramPaused,
+    NonceAlreadyUsed,
+    InvalidEd25519Instruction,
+    InvalidOrderSignature,
+    OrderAccountsMismatch,
+    WrongNonceBitmapAddress,
+    InvalidBatchAccounts,
+    OutputSnapshotMismatch,
+    MinOutputNotReached,
+    InvalidTransferFee,
+    PermanentDelegateNotAllowed,
+    NonTransferableNotAllowed,
+    DefaultAccountStateFrozenNotAllowed,
+    PausableNotAllowed,
+    ConfidentialTransferNotAllowed,
+    InvalidSweepAccounts,
+    InvalidSlippageShareBps,
+    InvalidMakerWsolVault,
+    InsufficientWsolVaultLamports,
+    InvalidVaultOperator,
+    InvalidMakerDelegate,
+    DelegateExpired,
+    DelegateMintNotAllowed,
+    DelegateFillLimitExceeded,
+    DelegateDailyLimitExceeded,
+    DelegateNativeSolNotAllowed,
+    TooManyDelegateMints,
+    MakerVolumeCapExceeded,
+    InvalidRiskWindow,
+    InvalidTakerOrderAccounts,
+    RelayerFeeExceeded,
+    MissingRelayerFeeRecipient,
+    NotUpgradeAuthority,
+    SharedAccountOwnerNotSigner,
+    WrongMakerRiskStateAddress,
+    MissingAssertMinOutput
 }
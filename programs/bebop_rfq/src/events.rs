@@ -0,0 +1,85 @@
+use anchor_lang::prelude::*;
+
+use crate::bebop_rfq::{AmountWithExpiry, SharedAccountSeeds};
+
+/// Bumped on every layout change of Bebop events
+pub const EVENT_VERSION: u8 = 1;
+
+/// How one side of a swap was settled
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferKind {
+    /// Token account to token account
+    Token,
+    /// Lamports from wallet to wallet
+    NativeSol,
+    /// Lamports wrapped into recipient WSOL token account
+    WrapSol,
+    /// WSOL unwrapped into recipient lamports
+    UnwrapSol,
+}
+
+impl TransferKind {
+    pub fn new(from_token_account: bool, to_token_account: bool) -> Self {
+        match (from_token_account, to_token_account) {
+            (true, true) => TransferKind::Token,
+            (false, false) => TransferKind::NativeSol,
+            (false, true) => TransferKind::WrapSol,
+            (true, false) => TransferKind::UnwrapSol,
+        }
+    }
+}
+
+/// One maker fill of `swap` or `swap_signed`, emitted with `emit_cpi!`
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BebopSwap {
+    pub version: u8,
+    pub event_id: u64,
+    pub maker_address: Pubkey,
+    pub taker_token: Pubkey,
+    pub maker_token: Pubkey,
+    pub filled_taker_amount: u64,
+    pub filled_maker_amount: u64,
+    pub fee_amount: u64,
+    pub taker: Pubkey,
+    pub receiver: Pubkey,
+    /// Quoted taker amount
+    pub input_amount: u64,
+    /// Index of the selected `output_amounts` tier
+    pub output_tier: u8,
+    pub output_amount: AmountWithExpiry,
+    /// Maker amount was scaled down to a taker amount below `input_amount`
+    pub partial_fill: bool,
+    /// Taker to maker side
+    pub input_transfer: TransferKind,
+    /// Maker to receiver side
+    pub output_transfer: TransferKind,
+    /// Seeds of `taker` when it's shared-pda account
+    pub shared_account: Option<SharedAccountSeeds>,
+    /// Quoted taker amount above `input_amount`
+    pub positive_slippage_amount: u64,
+    /// Extra maker output paid to the receiver for its share of positive slippage
+    pub positive_slippage_output_amount: u64,
+    /// Maker output paid to the fee payer, deducted from the receiver and counted in `filled_maker_amount`
+    pub relayer_fee_amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BatchFill {
+    pub maker_address: Pubkey,
+    pub filled_taker_amount: u64,
+    pub filled_maker_amount: u64,
+    pub fee_amount: u64,
+}
+
+/// Every maker fill of `swap_batch`, emitted with `emit_cpi!`
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BebopSwapBatch {
+    pub version: u8,
+    pub event_id: u64,
+    pub taker_token: Pubkey,
+    pub maker_token: Pubkey,
+    pub fills: Vec<BatchFill>,
+    pub fee_amount: u64,
+}
@@ -0,0 +1,64 @@
+use anchor_lang::{prelude::*, solana_program::bpf_loader_upgradeable};
+
+use crate::{error::BebopError, state::{Config, CONFIG}};
+
+
+pub fn handle_initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey, fee_recipient: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.admin = admin;
+    config.fee_recipient = fee_recipient;
+    config.paused = false;
+    config.bump = ctx.bumps.config;
+    Ok(())
+}
+
+pub fn handle_set_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+    ctx.accounts.config.admin = new_admin;
+    Ok(())
+}
+
+pub fn handle_set_fee_recipient(ctx: Context<UpdateConfig>, fee_recipient: Pubkey) -> Result<()> {
+    ctx.accounts.config.fee_recipient = fee_recipient;
+    Ok(())
+}
+
+pub fn handle_set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+    ctx.accounts.config.paused = paused;
+    Ok(())
+}
+
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    /// Program upgrade authority, nobody else can front-run the first admin
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(payer.key()) @ BebopError::NotUpgradeAuthority,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [CONFIG],
+        bump,
+    )]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [CONFIG],
+        bump = config.bump,
+        has_one = admin,
+    )]
+    pub config: Account<'info, Config>,
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    bebop_rfq::DelegateLimits,
+    error::BebopError,
+    state::{MakerDelegate, MAKER_DELEGATE, MAX_DELEGATE_MINTS},
+};
+
+
+pub fn handle_set_delegate(ctx: Context<SetDelegate>, delegate: Pubkey, limits: DelegateLimits) -> Result<()> {
+    require!(limits.mints.len() <= MAX_DELEGATE_MINTS, BebopError::TooManyDelegateMints);
+    let maker_delegate = &mut ctx.accounts.maker_delegate;
+    maker_delegate.maker = ctx.accounts.maker.key();
+    maker_delegate.delegate = delegate;
+    maker_delegate.day_volumes = limits.mints.iter().map(|limit| maker_delegate.day_volume(&limit.mint)).collect();
+    maker_delegate.limits = limits;
+    maker_delegate.bump = ctx.bumps.maker_delegate;
+    Ok(())
+}
+
+pub fn handle_revoke_delegate(_ctx: Context<RevokeDelegate>) -> Result<()> {
+    Ok(())
+}
+
+
+#[derive(Accounts)]
+#[instruction(delegate: Pubkey)]
+pub struct SetDelegate<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    /// Created on first use, later calls replace the limits and keep today's volume of mints still listed
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + MakerDelegate::INIT_SPACE,
+        seeds = [MAKER_DELEGATE, maker.key().as_ref(), delegate.as_ref()],
+        bump,
+    )]
+    pub maker_delegate: Box<Account<'info, MakerDelegate>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDelegate<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        close = maker,
+        seeds = [MAKER_DELEGATE, maker.key().as_ref(), maker_delegate.delegate.as_ref()],
+        bump = maker_delegate.bump,
+        has_one = maker,
+    )]
+    pub maker_delegate: Box<Account<'info, MakerDelegate>>,
+}
@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::BebopError, state::{MakerRiskState, MAKER_RISK_STATE}};
+
+
+pub fn handle_set_risk_limit(ctx: Context<SetRiskLimit>, window_slots: u64, max_volume: u64) -> Result<()> {
+    require!(window_slots > 0, BebopError::InvalidRiskWindow);
+    let maker_risk_state = &mut ctx.accounts.maker_risk_state;
+    if maker_risk_state.window_slots != window_slots {
+        // volume filled so far counts into the first window of the new length
+        let slot = Clock::get()?.slot;
+        let rolling_volume = if maker_risk_state.window_slots > 0 { maker_risk_state.rolling_volume(slot) } else { 0 };
+        maker_risk_state.window_start_slot = slot;
+        maker_risk_state.current_volume = rolling_volume;
+        maker_risk_state.previous_volume = 0;
+    }
+    maker_risk_state.maker = ctx.accounts.maker.key();
+    maker_risk_state.input_mint = ctx.accounts.input_mint.key();
+    maker_risk_state.output_mint = ctx.accounts.output_mint.key();
+    maker_risk_state.window_slots = window_slots;
+    maker_risk_state.max_volume = max_volume;
+    maker_risk_state.bump = ctx.bumps.maker_risk_state;
+    Ok(())
+}
+
+/// Adds maker output of a fill to maker's volume cap of the pair. Every fill passes the pair's
+/// `MakerRiskState` PDA with its canonical bump, a maker without a cap passes it uninitialized
+pub fn record_maker_risk_fill<'info>(
+    maker_risk_state: &AccountInfo<'info>,
+    maker: &Pubkey,
+    input_mint: &Pubkey,
+    output_mint: &Pubkey,
+    bump: u8,
+    amount: u64,
+    slot: u64,
+) -> Result<()> {
+    let maker_risk_state_address = |bump: u8| Pubkey::create_program_address(
+        &[MAKER_RISK_STATE, maker.as_ref(), input_mint.as_ref(), output_mint.as_ref(), &[bump]],
+        &crate::ID,
+    );
+    let expected_maker_risk_state = maker_risk_state_address(bump).map_err(|_| BebopError::WrongMakerRiskStateAddress)?;
+    require_keys_eq!(maker_risk_state.key(), expected_maker_risk_state, BebopError::WrongMakerRiskStateAddress);
+    if maker_risk_state.owner != &crate::ID {
+        // an uninitialized PDA of a non-canonical bump would skip the cap, no higher bump may be valid
+        require!(
+            (bump..u8::MAX).all(|lower_bump| maker_risk_state_address(lower_bump + 1).is_err()),
+            BebopError::WrongMakerRiskStateAddress
+        );
+        return Ok(());
+    }
+    let mut existing_maker_risk_state = MakerRiskState::try_deserialize(&mut &maker_risk_state.try_borrow_data()?[..])?;
+    // `set_risk_limit` stores the canonical bump
+    require_eq!(bump, existing_maker_risk_state.bump, BebopError::WrongMakerRiskStateAddress);
+    existing_maker_risk_state.record_fill(amount, slot)?;
+    existing_maker_risk_state.try_serialize(&mut &mut maker_risk_state.try_borrow_mut_data()?[..])
+}
+
+
+#[derive(Accounts)]
+pub struct SetRiskLimit<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + MakerRiskState::INIT_SPACE,
+        seeds = [MAKER_RISK_STATE, maker.key().as_ref(), input_mint.key().as_ref(), output_mint.key().as_ref()],
+        bump,
+    )]
+    pub maker_risk_state: Box<Account<'info, MakerRiskState>>,
+    /// CHECK: only used as seed
+    pub input_mint: UncheckedAccount<'info>,
+    /// CHECK: only used as seed
+    pub output_mint: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,119 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{instructions::utils::transfer, state::{MakerVault, MAKER_VAULT}};
+
+
+pub fn handle_deposit<'c: 'info, 'info>(ctx: Context<'_, '_, 'c, 'info, Deposit<'info>>, amount: u64) -> Result<()> {
+    let maker_vault = &mut ctx.accounts.maker_vault;
+    if maker_vault.maker == Pubkey::default() {
+        maker_vault.maker = ctx.accounts.maker.key();
+        maker_vault.mint = ctx.accounts.mint.key();
+        maker_vault.operator = ctx.accounts.maker.key();
+        maker_vault.token_account = ctx.accounts.vault_token_account.key();
+        maker_vault.bump = ctx.bumps.maker_vault;
+    }
+    if amount > 0 {
+        transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.maker_token_account.to_account_info(),
+            ctx.accounts.vault_token_account.to_account_info(),
+            ctx.accounts.maker.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            amount,
+            None,
+            ctx.remaining_accounts,
+        )?;
+    }
+    Ok(())
+}
+
+pub fn handle_withdraw<'c: 'info, 'info>(ctx: Context<'_, '_, 'c, 'info, Withdraw<'info>>, amount: u64) -> Result<()> {
+    let maker = ctx.accounts.maker.key();
+    let mint = ctx.accounts.mint.key();
+    let binding: [&[&[u8]]; 1] = [&[MAKER_VAULT, maker.as_ref(), mint.as_ref(), &[ctx.accounts.maker_vault.bump]]];
+    transfer(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.vault_token_account.to_account_info(),
+        ctx.accounts.maker_token_account.to_account_info(),
+        ctx.accounts.maker_vault.to_account_info(),
+        ctx.accounts.mint.to_account_info(),
+        amount,
+        Some(&binding),
+        ctx.remaining_accounts,
+    )
+}
+
+pub fn handle_set_vault_operator(ctx: Context<SetVaultOperator>, operator: Pubkey) -> Result<()> {
+    ctx.accounts.maker_vault.operator = operator;
+    Ok(())
+}
+
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + MakerVault::INIT_SPACE,
+        seeds = [MAKER_VAULT, maker.key().as_ref(), mint.key().as_ref()],
+        bump,
+    )]
+    pub maker_vault: Box<Account<'info, MakerVault>>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        seeds = [MAKER_VAULT, maker_vault.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = maker_vault,
+        token::token_program = token_program,
+    )]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = maker,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub maker_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        seeds = [MAKER_VAULT, maker.key().as_ref(), mint.key().as_ref()],
+        bump = maker_vault.bump,
+        has_one = maker,
+    )]
+    pub maker_vault: Box<Account<'info, MakerVault>>,
+    #[account(mut, address = maker_vault.token_account)]
+    pub vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = maker,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub maker_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultOperator<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MAKER_VAULT, maker.key().as_ref(), maker_vault.mint.as_ref()],
+        bump = maker_vault.bump,
+        has_one = maker,
+    )]
+    pub maker_vault: Box<Account<'info, MakerVault>>,
+}
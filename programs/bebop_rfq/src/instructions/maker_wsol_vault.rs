@@ -0,0 +1,104 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token::{self, spl_token::native_mint, Mint, Token, TokenAccount};
+
+use crate::{error::BebopError, state::{MakerWsolVault, MAKER_WSOL_VAULT}};
+
+
+pub fn handle_init_maker_wsol_vault(ctx: Context<InitMakerWsolVault>, lamports: u64) -> Result<()> {
+    let maker_wsol_vault = &mut ctx.accounts.maker_wsol_vault;
+    maker_wsol_vault.maker = ctx.accounts.maker.key();
+    maker_wsol_vault.token_account = ctx.accounts.vault_token_account.key();
+    maker_wsol_vault.bump = ctx.bumps.maker_wsol_vault;
+    if lamports > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.maker.to_account_info(),
+                    to: ctx.accounts.maker_wsol_vault.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+    }
+    Ok(())
+}
+
+pub fn handle_withdraw_maker_wsol_vault(ctx: Context<WithdrawMakerWsolVault>, lamports: u64, wsol_amount: u64) -> Result<()> {
+    if wsol_amount > 0 {
+        let maker = ctx.accounts.maker.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[MAKER_WSOL_VAULT, maker.as_ref(), &[ctx.accounts.maker_wsol_vault.bump]]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.vault_token_account.to_account_info(),
+                    to: ctx.accounts.maker_wsol_token_account.to_account_info(),
+                    authority: ctx.accounts.maker_wsol_vault.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            wsol_amount,
+        )?;
+    }
+    if lamports > 0 {
+        debit_maker_wsol_vault(
+            &ctx.accounts.maker_wsol_vault.to_account_info(),
+            &ctx.accounts.maker.to_account_info(),
+            lamports,
+        )?;
+    }
+    Ok(())
+}
+
+/// Moves vault lamports above rent directly, vault is owned by the program
+pub fn debit_maker_wsol_vault(maker_wsol_vault: &AccountInfo, destination: &AccountInfo, lamports: u64) -> Result<()> {
+    let available = maker_wsol_vault.lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(maker_wsol_vault.data_len()));
+    require!(lamports <= available, BebopError::InsufficientWsolVaultLamports);
+    **maker_wsol_vault.try_borrow_mut_lamports()? -= lamports;
+    **destination.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+
+#[derive(Accounts)]
+pub struct InitMakerWsolVault<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + MakerWsolVault::INIT_SPACE,
+        seeds = [MAKER_WSOL_VAULT, maker.key().as_ref()],
+        bump,
+    )]
+    pub maker_wsol_vault: Box<Account<'info, MakerWsolVault>>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        seeds = [MAKER_WSOL_VAULT, maker_wsol_vault.key().as_ref()],
+        bump,
+        token::mint = wsol_mint,
+        token::authority = maker_wsol_vault,
+        token::token_program = token_program,
+    )]
+    pub vault_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(address = native_mint::ID)]
+    pub wsol_mint: Box<Account<'info, Mint>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawMakerWsolVault<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(mut, seeds = [MAKER_WSOL_VAULT, maker.key().as_ref()], bump = maker_wsol_vault.bump, has_one = maker)]
+    pub maker_wsol_vault: Box<Account<'info, MakerWsolVault>>,
+    #[account(mut, address = maker_wsol_vault.token_account)]
+    pub vault_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, token::mint = native_mint::ID, token::authority = maker)]
+    pub maker_wsol_token_account: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
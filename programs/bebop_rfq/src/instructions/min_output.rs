@@ -0,0 +1,112 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions::{self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked},
+    Discriminator,
+};
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::{error::BebopError, state::{OutputSnapshot, OUTPUT_SNAPSHOT}};
+
+
+pub fn handle_record_output_balance(ctx: Context<RecordOutputBalance>) -> Result<()> {
+    // the snapshot can't outlive the transaction: a later `assert_min_output` of the taker closes it
+    let taker = ctx.accounts.taker.key();
+    let current_index = load_current_index_checked(&ctx.accounts.instructions_sysvar)? as usize;
+    let asserted = (current_index + 1..)
+        .map_while(|index| load_instruction_at_checked(index, &ctx.accounts.instructions_sysvar).ok())
+        .any(|instruction| {
+            instruction.program_id == crate::ID
+                && instruction.data.starts_with(&crate::instruction::AssertMinOutput::DISCRIMINATOR)
+                && instruction.accounts.first().is_some_and(|account| account.pubkey == taker)
+        });
+    require!(asserted, BebopError::MissingAssertMinOutput);
+    let balance = get_receiver_balance(
+        &ctx.accounts.receiver, ctx.accounts.receiver_output_mint_token_account.as_deref()
+    );
+    let output_snapshot = &mut ctx.accounts.output_snapshot;
+    output_snapshot.taker = ctx.accounts.taker.key();
+    output_snapshot.receiver = ctx.accounts.receiver.key();
+    output_snapshot.output_mint = ctx.accounts.output_mint.key();
+    output_snapshot.receiver_output_mint_token_account = ctx.accounts.receiver_output_mint_token_account
+        .as_ref()
+        .map(|token_account| token_account.key());
+    output_snapshot.balance = balance;
+    output_snapshot.bump = ctx.bumps.output_snapshot;
+    Ok(())
+}
+
+pub fn handle_assert_min_output(ctx: Context<AssertMinOutput>, min_output_amount: u64) -> Result<()> {
+    require!(
+        ctx.accounts.output_snapshot.receiver_output_mint_token_account
+            == ctx.accounts.receiver_output_mint_token_account.as_ref().map(|token_account| token_account.key()),
+        BebopError::OutputSnapshotMismatch
+    );
+    let balance = get_receiver_balance(
+        &ctx.accounts.receiver, ctx.accounts.receiver_output_mint_token_account.as_deref()
+    );
+    let received_amount = balance.saturating_sub(ctx.accounts.output_snapshot.balance);
+    require!(received_amount >= min_output_amount, BebopError::MinOutputNotReached);
+    Ok(())
+}
+
+fn get_receiver_balance(receiver: &AccountInfo, receiver_output_mint_token_account: Option<&InterfaceAccount<TokenAccount>>) -> u64 {
+    match receiver_output_mint_token_account {
+        Some(token_account) => token_account.amount,
+        None => receiver.lamports(),
+    }
+}
+
+
+#[derive(Accounts)]
+pub struct RecordOutputBalance<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    /// CHECK: can be same as taker address or any other address
+    pub receiver: UncheckedAccount<'info>,
+    /// CHECK: Validated by token account mint check, native mint for native SOL output
+    pub output_mint: UncheckedAccount<'info>,
+    /// None for native SOL output
+    #[account(
+        token::authority = receiver,
+        token::mint = output_mint,
+    )]
+    pub receiver_output_mint_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = 8 + OutputSnapshot::INIT_SPACE,
+        seeds = [OUTPUT_SNAPSHOT, taker.key().as_ref()],
+        bump,
+    )]
+    pub output_snapshot: Account<'info, OutputSnapshot>,
+    /// CHECK: instructions sysvar, looked up for the closing `assert_min_output`
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AssertMinOutput<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    /// CHECK: checked against output_snapshot
+    pub receiver: UncheckedAccount<'info>,
+    /// CHECK: checked against output_snapshot
+    pub output_mint: UncheckedAccount<'info>,
+    /// None for native SOL output
+    #[account(
+        token::authority = receiver,
+        token::mint = output_mint,
+    )]
+    pub receiver_output_mint_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        seeds = [OUTPUT_SNAPSHOT, taker.key().as_ref()],
+        bump = output_snapshot.bump,
+        has_one = taker,
+        has_one = receiver @ BebopError::OutputSnapshotMismatch,
+        has_one = output_mint @ BebopError::OutputSnapshotMismatch,
+        close = taker,
+    )]
+    pub output_snapshot: Account<'info, OutputSnapshot>,
+}
@@ -1,5 +1,27 @@
+mod config;
+mod maker_delegate;
+mod maker_risk_state;
+mod maker_vault;
+mod maker_wsol_vault;
+mod min_output;
+mod nonces;
 mod swap;
+mod swap_batch;
+mod swap_from_vault;
+mod swap_signed;
+mod sweep_shared;
 mod utils;
 
+pub use config::*;
+pub use maker_delegate::*;
+pub use maker_risk_state::*;
+pub use maker_vault::*;
+pub use maker_wsol_vault::*;
+pub use min_output::*;
+pub use nonces::*;
 pub use swap::*;
+pub use swap_batch::*;
+pub use swap_from_vault::*;
+pub use swap_signed::*;
+pub use sweep_shared::*;
 pub use utils::*;
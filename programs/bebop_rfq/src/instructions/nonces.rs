@@ -0,0 +1,93 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::associated_token::spl_associated_token_account::tools::account::create_pda_account;
+
+use crate::{error::BebopError, state::{NonceBitmap, NONCE_BITMAP, TAKER_NONCE_BITMAP}};
+
+
+pub fn handle_cancel_nonces(ctx: Context<CancelNonces>, word_pos: u64, mask: [u8; 32]) -> Result<()> {
+    let nonce_bitmap = &mut ctx.accounts.nonce_bitmap;
+    nonce_bitmap.owner = ctx.accounts.maker.key();
+    nonce_bitmap.word_pos = word_pos;
+    nonce_bitmap.bump = ctx.bumps.nonce_bitmap;
+    nonce_bitmap.cancel(mask);
+    Ok(())
+}
+
+pub fn handle_cancel_taker_nonces(ctx: Context<CancelTakerNonces>, word_pos: u64, mask: [u8; 32]) -> Result<()> {
+    let nonce_bitmap = &mut ctx.accounts.nonce_bitmap;
+    nonce_bitmap.owner = ctx.accounts.taker.key();
+    nonce_bitmap.word_pos = word_pos;
+    nonce_bitmap.bump = ctx.bumps.nonce_bitmap;
+    nonce_bitmap.cancel(mask);
+    Ok(())
+}
+
+
+/// Same as `init_if_needed` nonce bitmap in `Swap`, for bitmaps passed in remaining accounts
+pub fn use_nonce_in_account<'info>(
+    nonce_bitmap: &'info AccountInfo<'info>,
+    maker: Pubkey,
+    nonce: u64,
+    bump: u8,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+) -> Result<()> {
+    let word_pos = NonceBitmap::word_pos(nonce);
+    let expected_nonce_bitmap = Pubkey::create_program_address(
+        &[NONCE_BITMAP, maker.as_ref(), &word_pos.to_le_bytes(), &[bump]],
+        &crate::ID,
+    ).map_err(|_| BebopError::WrongNonceBitmapAddress)?;
+    require_keys_eq!(nonce_bitmap.key(), expected_nonce_bitmap, BebopError::WrongNonceBitmapAddress);
+    if nonce_bitmap.owner == &system_program::ID {
+        create_pda_account(
+            &payer,
+            &Rent::get()?,
+            8 + NonceBitmap::INIT_SPACE,
+            &crate::ID,
+            &system_program,
+            nonce_bitmap,
+            &[NONCE_BITMAP, maker.as_ref(), &word_pos.to_le_bytes(), &[bump]],
+        )?;
+        let mut new_nonce_bitmap = NonceBitmap { owner: maker, word_pos, bitmap: [0; 32], bump };
+        new_nonce_bitmap.use_nonce(nonce)?;
+        new_nonce_bitmap.try_serialize(&mut &mut nonce_bitmap.try_borrow_mut_data()?[..])?;
+    } else {
+        let mut existing_nonce_bitmap: Account<NonceBitmap> = Account::try_from(nonce_bitmap)?;
+        existing_nonce_bitmap.use_nonce(nonce)?;
+        existing_nonce_bitmap.exit(&crate::ID)?;
+    }
+    Ok(())
+}
+
+
+#[derive(Accounts)]
+#[instruction(word_pos: u64)]
+pub struct CancelNonces<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = 8 + NonceBitmap::INIT_SPACE,
+        seeds = [NONCE_BITMAP, maker.key().as_ref(), &word_pos.to_le_bytes()],
+        bump,
+    )]
+    pub nonce_bitmap: Account<'info, NonceBitmap>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(word_pos: u64)]
+pub struct CancelTakerNonces<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = 8 + NonceBitmap::INIT_SPACE,
+        seeds = [TAKER_NONCE_BITMAP, taker.key().as_ref(), &word_pos.to_le_bytes()],
+        bump,
+    )]
+    pub nonce_bitmap: Account<'info, NonceBitmap>,
+    pub system_program: Program<'info, System>,
+}
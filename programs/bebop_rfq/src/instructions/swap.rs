@@ -1,68 +1,149 @@
-use std::cmp::min;
-
-use anchor_lang::{prelude::*, system_program};
-use anchor_spl::{
-    token::{
-        self,
-        spl_token::{self, native_mint},
-    },
-    token_2022::spl_token_2022::{
-        self,
-        extension::{
-            transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
-        },
-    },
-    token_interface::{self, spl_pod::primitives::PodU16, TokenAccount, TokenInterface},
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions::{self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked},
+    system_program,
 };
-use crate::{bebop_rfq::AmountWithExpiry, error::BebopError, instructions::utils::{transfer, unwrap_sol}, SHARED_ACCOUNT};
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface};
+use crate::{bebop_rfq::{AmountWithExpiry, PdaBumps, PositiveSlippage, RelayerFee, SharedAccountSeeds, TakerOrder, TransferFeeMode}, error::BebopError, events::{BebopSwap, TransferKind, EVENT_VERSION}, instructions::{maker_risk_state::record_maker_risk_fill, utils::{get_fee_amount, get_filled_maker_amount, get_filled_taker_amount, get_output_amount, get_positive_slippage_amount, get_transfer_amounts, is_native_mint, transfer, unwrap_sol, verify_ed25519_instruction, BPS_DENOMINATOR, MAKER_AUTHORITY, TAKER_AUTHORITY}}, state::{Config, MakerDelegate, NonceBitmap, CONFIG, MAKER_DELEGATE, NONCE_BITMAP, TAKER_NONCE_BITMAP}, SHARED_ACCOUNT};
 
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_swap<'c: 'info, 'info>(
     ctx: Context<'_, '_, 'c, 'info, Swap<'info>>,
     input_amount: u64,
     output_amounts: Vec<AmountWithExpiry>,
     event_id: u64,
+    fee_bps: u16,
+    nonce: u64,
+    transfer_fee_mode: TransferFeeMode,
+    bumps: PdaBumps,
+    shared_account: Option<SharedAccountSeeds>,
+    positive_slippage: Option<PositiveSlippage>,
+    relayer_fee: Option<RelayerFee>,
+    taker_nonce: Option<u64>,
 ) -> Result<()> {
-    let now = Clock::get()?.unix_timestamp as u64;
-    let mut output_amount: u64 = 0;
-    for (i, amount_with_expiry) in output_amounts.iter().enumerate() {
+    require!(!ctx.accounts.config.paused, BebopError::ProgramPaused);
+    // maker's hot key signs instead of the maker, maker output moves through `maker-authority` delegation
+    let delegated = !ctx.accounts.maker.is_signer;
+    if delegated {
+        let (Some(delegate), Some(maker_delegate), Some(_)) = (
+            &ctx.accounts.delegate,
+            &ctx.accounts.maker_delegate,
+            &ctx.accounts.maker_authority,
+        ) else {
+            return err!(BebopError::InvalidMakerDelegate);
+        };
+        require_keys_eq!(delegate.key(), maker_delegate.delegate, BebopError::InvalidMakerDelegate);
+        maker_delegate.authorize(&ctx.accounts.input_mint.key(), &ctx.accounts.output_mint.key(), Clock::get()?.slot)?;
+        // maker lamports and temporary WSOL account need maker signature
         require!(
-            i == 0 || 
-            (amount_with_expiry.amount <= output_amounts[i - 1].amount && amount_with_expiry.expiry > output_amounts[i - 1].expiry),
-            BebopError::InvalidOutputAmount
+            ctx.accounts.maker_output_mint_token_account.is_some()
+                && ctx.accounts.receiver_output_mint_token_account.is_some()
+                && (ctx.accounts.maker_input_mint_token_account.is_some() || ctx.accounts.taker_input_mint_token_account.is_none()),
+            BebopError::DelegateNativeSolNotAllowed
         );
-        if amount_with_expiry.expiry >= now {
-            output_amount = amount_with_expiry.amount;
-            break;
-        }
     }
-    require!(output_amount > 0, BebopError::OrderExpired);
-    let mut bump: u8 = 0;
-    let filled_taker_amount: u64;
-    if !&ctx.accounts.taker.is_signer{
-        let (expected_pda_address, _bump) = Pubkey::find_program_address(
-            &[SHARED_ACCOUNT],
-            &crate::ID,
-        );
-        bump = _bump;
-        require_keys_eq!(ctx.accounts.taker.key(), expected_pda_address, BebopError::WrongSharedAccountAddress);
-        filled_taker_amount = match &ctx.accounts.taker_input_mint_token_account {
-            Some(token_acc) => token_acc.amount,
-            None => ctx.accounts.taker.lamports(),
+    // taker signed `TakerOrder` is verified by Ed25519 program in the previous instruction
+    if let Some(instructions_sysvar) = &ctx.accounts.instructions_sysvar {
+        let (Some(taker_nonce), Some(taker_nonce_bitmap)) = (taker_nonce, &mut ctx.accounts.taker_nonce_bitmap) else {
+            return err!(BebopError::InvalidTakerOrderAccounts);
         };
-    } else {
-        filled_taker_amount = input_amount;
+        let taker_order = TakerOrder {
+            taker: ctx.accounts.taker.key(),
+            receiver: ctx.accounts.receiver.key(),
+            maker: ctx.accounts.maker.key(),
+            input_mint: ctx.accounts.input_mint.key(),
+            output_mint: ctx.accounts.output_mint.key(),
+            input_amount,
+            output_amounts: output_amounts.clone(),
+            nonce,
+            fee_bps,
+            fee_recipient_token_account: ctx.accounts.fee_recipient_token_account.as_ref().map_or(Pubkey::default(), |account| account.key()),
+            transfer_fee_mode,
+            max_relayer_fee: relayer_fee.map_or(0, |relayer_fee| relayer_fee.max_amount),
+            relayer: relayer_fee.map_or(Pubkey::default(), |_| ctx.accounts.payer.key()),
+            taker_nonce,
+        };
+        let current_index = load_current_index_checked(instructions_sysvar)? as usize;
+        require!(current_index > 0, BebopError::InvalidEd25519Instruction);
+        let ed25519_instruction = load_instruction_at_checked(current_index - 1, instructions_sysvar)?;
+        verify_ed25519_instruction(&ed25519_instruction, &taker_order.taker, &taker_order.hash())?;
+        // taker's own nonces, the maker nonce only protects the maker
+        taker_nonce_bitmap.owner = taker_order.taker;
+        taker_nonce_bitmap.word_pos = NonceBitmap::word_pos(taker_nonce);
+        taker_nonce_bitmap.bump = ctx.bumps.taker_nonce_bitmap.unwrap_or_default();
+        taker_nonce_bitmap.use_nonce(taker_nonce)?;
     }
-    let binding: [&[&[u8]]; 1] = [&[SHARED_ACCOUNT, &[bump]]];
+    // gasless taker doesn't sign, its input moves through `taker-authority` delegation
+    let taker_delegated = !ctx.accounts.taker.is_signer && ctx.accounts.instructions_sysvar.is_some();
+    let shared_taker = !ctx.accounts.taker.is_signer && !taker_delegated;
+    if taker_delegated {
+        require!(
+            ctx.accounts.taker_authority.is_some()
+                && ctx.accounts.taker_input_mint_token_account.is_some()
+                && ctx.accounts.maker_input_mint_token_account.is_some(),
+            BebopError::InvalidTakerOrderAccounts
+        );
+    }
+    let nonce_bitmap = &mut ctx.accounts.nonce_bitmap;
+    nonce_bitmap.owner = ctx.accounts.maker.key();
+    nonce_bitmap.word_pos = NonceBitmap::word_pos(nonce);
+    nonce_bitmap.bump = ctx.bumps.nonce_bitmap;
+    nonce_bitmap.use_nonce(nonce)?;
+    require!((fee_bps as u64) < BPS_DENOMINATOR, BebopError::InvalidFeeBps);
+    let (output_tier, output_amount) = get_output_amount(&output_amounts)?;
+    let shared_account = shared_account.unwrap_or_default();
+    let route_id = shared_account.route_id.to_le_bytes();
+    let binding: [&[&[u8]]; 1] = [&[SHARED_ACCOUNT, shared_account.owner.as_ref(), &route_id, &[bumps.shared_account]]];
     let pda_seeds: Option<&[&[&[u8]]]> = Some(&binding);
+    let filled_taker_amount = if taker_delegated {
+        require!(input_amount > 0, BebopError::ZeroTakerAmount);
+        input_amount
+    } else {
+        get_filled_taker_amount(
+            &ctx.accounts.taker,
+            ctx.accounts.taker_input_mint_token_account.as_deref(),
+            input_amount,
+            binding[0],
+            ctx.accounts.shared_account_owner.as_ref(),
+        )?
+    };
+    // shared-pda balance above input_amount goes to the receiver instead of the maker
+    let filled_taker_amount = match (
+        &ctx.accounts.taker_input_mint_token_account,
+        &ctx.accounts.receiver_input_mint_token_account,
+    ) {
+        (Some(taker_input_mint_token_account), Some(receiver_input_mint_token_account))
+            if shared_taker && filled_taker_amount > input_amount =>
+        {
+            transfer(
+                ctx.accounts.input_token_program.to_account_info(),
+                taker_input_mint_token_account.to_account_info(),
+                receiver_input_mint_token_account.to_account_info(),
+                ctx.accounts.taker.to_account_info(),
+                ctx.accounts.input_mint.to_account_info(),
+                filled_taker_amount - input_amount,
+                pda_seeds,
+                ctx.remaining_accounts
+            )?;
+            input_amount
+        }
+        _ => filled_taker_amount,
+    };
+    // shared-pda account can only send its whole balance
+    let (sent_taker_amount, received_taker_amount) = get_transfer_amounts(
+        &ctx.accounts.input_mint,
+        &ctx.accounts.input_token_program,
+        filled_taker_amount,
+        if shared_taker { TransferFeeMode::Gross } else { transfer_fee_mode },
+    )?;
 
-    require!(filled_taker_amount > 0, BebopError::ZeroTakerAmount);
     match (
         &ctx.accounts.taker_input_mint_token_account,
         &ctx.accounts.maker_input_mint_token_account,
     ) {
         (None, None) => {
-            require_keys_eq!(ctx.accounts.input_mint.key(), native_mint::ID, BebopError::InvalidNativeTokenAddress);
+            require!(is_native_mint(&ctx.accounts.input_mint.key()), BebopError::InvalidNativeTokenAddress);
 
             system_program::transfer(
                 CpiContext::new(
@@ -76,7 +157,7 @@ pub fn handle_swap<'c: 'info, 'info>(
             )?;
         }
         (None, Some(maker_input_mint_token_account)) => {
-            require_keys_eq!(ctx.accounts.input_mint.key(), native_mint::ID, BebopError::InvalidNativeTokenAddress);
+            require!(is_native_mint(&ctx.accounts.input_mint.key()), BebopError::InvalidNativeTokenAddress);
 
             system_program::transfer(
                 CpiContext::new(
@@ -88,52 +169,115 @@ pub fn handle_swap<'c: 'info, 'info>(
                 ),
                 filled_taker_amount,
             )?;
-            token::sync_native(CpiContext::new(
+            token_interface::sync_native(CpiContext::new(
                 ctx.accounts.input_token_program.to_account_info(),
-                token::SyncNative {
+                token_interface::SyncNative {
                     account: maker_input_mint_token_account.to_account_info(),
                 },
             ))?;
         }
         (Some(taker_input_mint_token_account), None) => {
-            require_keys_eq!(ctx.accounts.input_mint.key(), native_mint::ID, BebopError::InvalidNativeTokenAddress);
+            require!(is_native_mint(&ctx.accounts.input_mint.key()), BebopError::InvalidNativeTokenAddress);
 
             unwrap_sol(
                 ctx.accounts.maker.to_account_info(),
                 ctx.accounts.taker.to_account_info(),
                 taker_input_mint_token_account.to_account_info(),
                 None,
-                ctx.remaining_accounts.iter().next(),
+                ctx.remaining_accounts,
                 ctx.accounts.input_mint.to_account_info(),
                 ctx.accounts.input_token_program.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
                 filled_taker_amount,
+                bumps.temporary_wsol_token_account,
             )?;
         }
-        (Some(taker_input_mint_token_account), Some(maker_input_mint_token_account)) => transfer(
-            ctx.accounts.input_token_program.to_account_info(),
-            taker_input_mint_token_account.to_account_info(),
-            maker_input_mint_token_account.to_account_info(),
-            ctx.accounts.taker.to_account_info(),
-            ctx.accounts.input_mint.to_account_info(),
-            filled_taker_amount,
-            if ctx.accounts.taker.is_signer {None} else {pda_seeds}
-        )?,
+        (Some(taker_input_mint_token_account), Some(maker_input_mint_token_account)) => {
+            let taker_authority_binding: [&[&[u8]]; 1] = [&[TAKER_AUTHORITY, &[ctx.bumps.taker_authority.unwrap_or_default()]]];
+            let (taker_authority, taker_seeds) = match &ctx.accounts.taker_authority {
+                Some(taker_authority) if taker_delegated => (taker_authority.to_account_info(), Some(&taker_authority_binding[..])),
+                _ => (ctx.accounts.taker.to_account_info(), if shared_taker {pda_seeds} else {None}),
+            };
+            transfer(
+                ctx.accounts.input_token_program.to_account_info(),
+                taker_input_mint_token_account.to_account_info(),
+                maker_input_mint_token_account.to_account_info(),
+                taker_authority,
+                ctx.accounts.input_mint.to_account_info(),
+                sent_taker_amount,
+                taker_seeds,
+                ctx.remaining_accounts
+            )?
+        }
     }
 
-    let filled_maker_amount: u64 = if filled_taker_amount < input_amount {
-        ((output_amount as u128 * filled_taker_amount as u128) / input_amount as u128) as u64
-    } else {
-        // max maker_amount is output_amount, even if filled_taker_amount > input_amount
-        output_amount
+    let filled_taker_amount = received_taker_amount;
+    let quoted_taker_amount = match transfer_fee_mode {
+        TransferFeeMode::Gross => sent_taker_amount,
+        TransferFeeMode::Net => received_taker_amount,
     };
+    let positive_slippage_output_amount = get_positive_slippage_amount(
+        output_amount.amount,
+        quoted_taker_amount,
+        input_amount,
+        positive_slippage.as_ref(),
+    )?;
+    let filled_maker_amount: u64 = get_filled_maker_amount(output_amount.amount, quoted_taker_amount, input_amount)
+        .saturating_add(positive_slippage_output_amount);
     require!(filled_maker_amount > 0, BebopError::ZeroMakerAmount);
+    if let (true, Some(maker_delegate)) = (delegated, &mut ctx.accounts.maker_delegate) {
+        maker_delegate.record_fill(&ctx.accounts.output_mint.key(), filled_maker_amount, Clock::get()?.unix_timestamp)?;
+    }
+    record_maker_risk_fill(
+        &ctx.accounts.maker_risk_state,
+        &ctx.accounts.maker.key(),
+        &ctx.accounts.input_mint.key(),
+        &ctx.accounts.output_mint.key(),
+        bumps.maker_risk_state,
+        filled_maker_amount,
+        Clock::get()?.slot,
+    )?;
+    // protocol fee and relayer fee are taken from the output leg, receiver gets the rest
+    let fee_amount: u64 = get_fee_amount(filled_maker_amount, fee_bps);
+    let relayer_fee_amount = match relayer_fee {
+        Some(relayer_fee) => {
+            require!(
+                relayer_fee.amount <= relayer_fee.max_amount && relayer_fee.amount <= filled_maker_amount - fee_amount,
+                BebopError::RelayerFeeExceeded
+            );
+            relayer_fee.amount
+        }
+        None => 0,
+    };
+    let (receiver_amount, received_receiver_amount) = get_transfer_amounts(
+        &ctx.accounts.output_mint,
+        &ctx.accounts.output_token_program,
+        filled_maker_amount - fee_amount - relayer_fee_amount,
+        transfer_fee_mode,
+    )?;
+    let (fee_amount, received_fee_amount) = get_transfer_amounts(
+        &ctx.accounts.output_mint,
+        &ctx.accounts.output_token_program,
+        fee_amount,
+        transfer_fee_mode,
+    )?;
+    let (relayer_fee_amount, received_relayer_fee_amount) = get_transfer_amounts(
+        &ctx.accounts.output_mint,
+        &ctx.accounts.output_token_program,
+        relayer_fee_amount,
+        transfer_fee_mode,
+    )?;
+    let maker_authority_binding: [&[&[u8]]; 1] = [&[MAKER_AUTHORITY, &[ctx.bumps.maker_authority.unwrap_or_default()]]];
+    let (maker_output_authority, maker_output_seeds): (AccountInfo<'info>, Option<&[&[&[u8]]]>) = match &ctx.accounts.maker_authority {
+        Some(maker_authority) if delegated => (maker_authority.to_account_info(), Some(&maker_authority_binding)),
+        _ => (ctx.accounts.maker.to_account_info(), None),
+    };
     match (
         &ctx.accounts.maker_output_mint_token_account,
         &ctx.accounts.receiver_output_mint_token_account,
     ) {
         (None, None) => {
-            require_keys_eq!(ctx.accounts.output_mint.key(), native_mint::ID, BebopError::InvalidNativeTokenAddress);
+            require!(is_native_mint(&ctx.accounts.output_mint.key()), BebopError::InvalidNativeTokenAddress);
 
             system_program::transfer(
                 CpiContext::new(
@@ -143,25 +287,26 @@ pub fn handle_swap<'c: 'info, 'info>(
                         to: ctx.accounts.receiver.to_account_info(),
                     },
                 ),
-                filled_maker_amount,
+                receiver_amount,
             )?;
         }
         (Some(maker_output_mint_token_account), None) => {
-            require_keys_eq!(ctx.accounts.output_mint.key(), native_mint::ID, BebopError::InvalidNativeTokenAddress);
+            require!(is_native_mint(&ctx.accounts.output_mint.key()), BebopError::InvalidNativeTokenAddress);
             unwrap_sol(
                 ctx.accounts.maker.to_account_info(),
                 ctx.accounts.maker.to_account_info(),
                 maker_output_mint_token_account.to_account_info(),
                 Some(ctx.accounts.receiver.to_account_info()),
-                ctx.remaining_accounts.iter().next(),
+                ctx.remaining_accounts,
                 ctx.accounts.output_mint.to_account_info(),
                 ctx.accounts.output_token_program.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
-                filled_maker_amount,
+                receiver_amount,
+                bumps.temporary_wsol_token_account,
             )?;
         }
         (None, Some(receiver_output_mint_token_account)) => {
-            require_keys_eq!(ctx.accounts.output_mint.key(), native_mint::ID, BebopError::InvalidNativeTokenAddress);
+            require!(is_native_mint(&ctx.accounts.output_mint.key()), BebopError::InvalidNativeTokenAddress);
 
             system_program::transfer(
                 CpiContext::new(
@@ -171,11 +316,11 @@ pub fn handle_swap<'c: 'info, 'info>(
                         to: receiver_output_mint_token_account.to_account_info(),
                     },
                 ),
-                filled_maker_amount,
+                receiver_amount,
             )?;
-            token::sync_native(CpiContext::new(
+            token_interface::sync_native(CpiContext::new(
                 ctx.accounts.output_token_program.to_account_info(),
-                token::SyncNative {
+                token_interface::SyncNative {
                     account: receiver_output_mint_token_account.to_account_info(),
                 },
             ))?;
@@ -184,32 +329,135 @@ pub fn handle_swap<'c: 'info, 'info>(
             ctx.accounts.output_token_program.to_account_info(),
             maker_output_mint_token_account.to_account_info(),
             receiver_output_mint_token_account.to_account_info(),
-            ctx.accounts.maker.to_account_info(),
+            maker_output_authority.clone(),
             ctx.accounts.output_mint.to_account_info(),
-            filled_maker_amount,
-            None
+            receiver_amount,
+            maker_output_seeds,
+            ctx.remaining_accounts
         )?,
     }
-    emit!(BebopSwap{
-        event_id: event_id,
+    if fee_amount > 0 {
+        let fee_recipient_token_account = ctx.accounts.fee_recipient_token_account
+            .as_ref()
+            .ok_or(BebopError::MissingFeeRecipient)?;
+        match &ctx.accounts.maker_output_mint_token_account {
+            None => {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.maker.to_account_info(),
+                            to: fee_recipient_token_account.to_account_info(),
+                        },
+                    ),
+                    fee_amount,
+                )?;
+                token_interface::sync_native(CpiContext::new(
+                    ctx.accounts.output_token_program.to_account_info(),
+                    token_interface::SyncNative {
+                        account: fee_recipient_token_account.to_account_info(),
+                    },
+                ))?;
+            }
+            Some(maker_output_mint_token_account) => transfer(
+                ctx.accounts.output_token_program.to_account_info(),
+                maker_output_mint_token_account.to_account_info(),
+                fee_recipient_token_account.to_account_info(),
+                maker_output_authority.clone(),
+                ctx.accounts.output_mint.to_account_info(),
+                fee_amount,
+                maker_output_seeds,
+                ctx.remaining_accounts
+            )?,
+        }
+    }
+    if relayer_fee_amount > 0 {
+        match &ctx.accounts.maker_output_mint_token_account {
+            // native SOL maker reimburses the fee payer in lamports
+            None => system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.maker.to_account_info(),
+                        to: ctx.accounts.payer.to_account_info(),
+                    },
+                ),
+                relayer_fee_amount,
+            )?,
+            Some(maker_output_mint_token_account) => {
+                let relayer_fee_token_account = ctx.accounts.relayer_fee_token_account
+                    .as_ref()
+                    .ok_or(BebopError::MissingRelayerFeeRecipient)?;
+                transfer(
+                    ctx.accounts.output_token_program.to_account_info(),
+                    maker_output_mint_token_account.to_account_info(),
+                    relayer_fee_token_account.to_account_info(),
+                    maker_output_authority,
+                    ctx.accounts.output_mint.to_account_info(),
+                    relayer_fee_amount,
+                    maker_output_seeds,
+                    ctx.remaining_accounts
+                )?
+            }
+        }
+    }
+    emit_cpi!(BebopSwap{
+        version: EVENT_VERSION,
+        event_id,
         maker_address: ctx.accounts.maker.key(),
         taker_token: ctx.accounts.input_mint.key(),
         maker_token: ctx.accounts.output_mint.key(),
         filled_taker_amount,
-        filled_maker_amount,
+        filled_maker_amount: received_receiver_amount + received_fee_amount + received_relayer_fee_amount,
+        fee_amount: received_fee_amount,
+        taker: ctx.accounts.taker.key(),
+        receiver: ctx.accounts.receiver.key(),
+        input_amount,
+        output_tier,
+        output_amount,
+        partial_fill: quoted_taker_amount < input_amount,
+        input_transfer: TransferKind::new(
+            ctx.accounts.taker_input_mint_token_account.is_some(),
+            ctx.accounts.maker_input_mint_token_account.is_some(),
+        ),
+        output_transfer: TransferKind::new(
+            ctx.accounts.maker_output_mint_token_account.is_some(),
+            ctx.accounts.receiver_output_mint_token_account.is_some(),
+        ),
+        shared_account: shared_taker.then_some(shared_account),
+        positive_slippage_amount: quoted_taker_amount.saturating_sub(input_amount),
+        positive_slippage_output_amount,
+        relayer_fee_amount: received_relayer_fee_amount,
     });
     Ok(())
 }
 
 
 
+#[event_cpi]
 #[derive(Accounts)]
+#[instruction(
+    input_amount: u64,
+    output_amounts: Vec<AmountWithExpiry>,
+    event_id: u64,
+    fee_bps: u16,
+    nonce: u64,
+    transfer_fee_mode: TransferFeeMode,
+    bumps: PdaBumps,
+    shared_account: Option<SharedAccountSeeds>,
+    positive_slippage: Option<PositiveSlippage>,
+    relayer_fee: Option<RelayerFee>,
+    taker_nonce: Option<u64>,
+)]
 pub struct Swap<'info> {
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
     /// CHECK: taker isn't Signer when it's shared-pda account 
     #[account(mut)]
     pub taker: UncheckedAccount<'info>,
+    /// CHECK: signs the swap, or `delegate` signs for it
     #[account(mut)]
-    pub maker: Signer<'info>,
+    pub maker: UncheckedAccount<'info>,
     /// CHECK: can be same as taker address or any other address 
     #[account(mut)]
     pub receiver: UncheckedAccount<'info>,
@@ -241,22 +489,80 @@ pub struct Swap<'info> {
         token::token_program = output_token_program
     )]
     pub maker_output_mint_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Required only when fee_bps > 0, wrapped SOL account for native SOL output
+    #[account(
+        mut,
+        token::authority = config.fee_recipient,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub fee_recipient_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Set to cap shared-pda taker at `input_amount`, the surplus is sent here
+    #[account(
+        mut,
+        token::authority = receiver,
+        token::mint = input_mint,
+        token::token_program = input_token_program
+    )]
+    pub receiver_input_mint_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + NonceBitmap::INIT_SPACE,
+        seeds = [NONCE_BITMAP, maker.key().as_ref(), &NonceBitmap::word_pos(nonce).to_le_bytes()],
+        bump,
+    )]
+    pub nonce_bitmap: Box<Account<'info, NonceBitmap>>,
     /// CHECK: Validated by token account mint check
     pub input_mint: UncheckedAccount<'info>,
     pub input_token_program: Interface<'info, TokenInterface>,
     /// CHECK: Validated by token account mint check
     pub output_mint: UncheckedAccount<'info>,
     pub output_token_program: Interface<'info, TokenInterface>,
+    /// Pays for the maker nonce bitmap on first use
+    #[account(mut)]
+    pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
-}
-
-#[event]
-struct BebopSwap {
-    event_id: u64,
-    maker_address: Pubkey,
-    taker_token: Pubkey,
-    maker_token: Pubkey,
-    filled_taker_amount: u64,
-    filled_maker_amount: u64,
+    /// Maker's hot key, required when maker doesn't sign
+    pub delegate: Option<Signer<'info>>,
+    #[account(
+        mut,
+        seeds = [MAKER_DELEGATE, maker.key().as_ref(), maker_delegate.delegate.as_ref()],
+        bump = maker_delegate.bump,
+        has_one = maker,
+    )]
+    pub maker_delegate: Option<Box<Account<'info, MakerDelegate>>>,
+    /// CHECK: delegate of maker token accounts, moves maker output on delegated fills
+    #[account(seeds = [MAKER_AUTHORITY], bump)]
+    pub maker_authority: Option<UncheckedAccount<'info>>,
+    /// CHECK: maker's `MakerRiskState` PDA of the mint pair, uninitialized when the maker has no cap.
+    /// Checked by `record_maker_risk_fill`
+    #[account(mut)]
+    pub maker_risk_state: UncheckedAccount<'info>,
+    /// CHECK: instructions sysvar, binds the fill to a taker signed `TakerOrder`
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+    /// CHECK: delegate of taker input token account, moves taker input when taker doesn't sign
+    #[account(seeds = [TAKER_AUTHORITY], bump)]
+    pub taker_authority: Option<UncheckedAccount<'info>>,
+    /// Taker's `NonceBitmap` of `taker_nonce`, required with `instructions_sysvar`
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + NonceBitmap::INIT_SPACE,
+        seeds = [TAKER_NONCE_BITMAP, taker.key().as_ref(), &NonceBitmap::word_pos(taker_nonce.unwrap_or_default()).to_le_bytes()],
+        bump,
+    )]
+    pub taker_nonce_bitmap: Option<Box<Account<'info, NonceBitmap>>>,
+    /// Required only for relayer fee paid from maker token account, wrapped SOL account for WSOL maker output
+    #[account(
+        mut,
+        token::authority = payer,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub relayer_fee_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// Owner of the `shared-account` PDA taker, required when the taker doesn't sign
+    pub shared_account_owner: Option<Signer<'info>>,
 }
 
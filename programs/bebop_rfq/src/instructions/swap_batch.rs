@@ -0,0 +1,211 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface};
+use crate::{
+    bebop_rfq::{BatchLeg, TransferFeeMode},
+    error::BebopError,
+    events::{BatchFill, BebopSwapBatch, EVENT_VERSION},
+    instructions::{
+        maker_risk_state::record_maker_risk_fill,
+        nonces::use_nonce_in_account,
+        utils::{get_fee_amount, get_filled_maker_amount, get_output_amount, get_transfer_amounts, is_native_mint, transfer, BPS_DENOMINATOR},
+    },
+    state::{Config, CONFIG},
+};
+
+/// maker, maker_input_mint_token_account, maker_output_mint_token_account, nonce_bitmap, maker_risk_state
+pub const BATCH_LEG_ACCOUNTS: usize = 5;
+
+
+pub fn handle_swap_batch<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, SwapBatch<'info>>,
+    legs: Vec<BatchLeg>,
+    event_id: u64,
+    fee_bps: u16,
+    transfer_fee_mode: TransferFeeMode,
+) -> Result<()> {
+    require!(!ctx.accounts.config.paused, BebopError::ProgramPaused);
+    require!((fee_bps as u64) < BPS_DENOMINATOR, BebopError::InvalidFeeBps);
+    require!(
+        !legs.is_empty() && ctx.remaining_accounts.len() >= legs.len() * BATCH_LEG_ACCOUNTS,
+        BebopError::InvalidBatchAccounts
+    );
+    let (legs_accounts, transfer_hook_accounts) = ctx.remaining_accounts.split_at(legs.len() * BATCH_LEG_ACCOUNTS);
+
+    let mut fills: Vec<BatchFill> = Vec::with_capacity(legs.len());
+    let mut total_fee_amount: u64 = 0;
+    for (leg, leg_accounts) in legs.iter().zip(legs_accounts.chunks_exact(BATCH_LEG_ACCOUNTS)) {
+        let [maker, maker_input_mint_token_account, maker_output_mint_token_account, nonce_bitmap, maker_risk_state] = leg_accounts else {
+            return err!(BebopError::InvalidBatchAccounts);
+        };
+        require!(maker.is_signer, ErrorCode::AccountNotSigner);
+        check_maker_token_account(maker_input_mint_token_account, maker.key, &ctx.accounts.input_mint, &ctx.accounts.input_token_program)?;
+        check_maker_token_account(maker_output_mint_token_account, maker.key, &ctx.accounts.output_mint, &ctx.accounts.output_token_program)?;
+        use_nonce_in_account(
+            nonce_bitmap,
+            maker.key(),
+            leg.nonce,
+            leg.nonce_bitmap_bump,
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        let output_amount: u64 = get_output_amount(&leg.output_amounts)?.1.amount;
+        require!(leg.input_amount > 0, BebopError::ZeroTakerAmount);
+        let (sent_taker_amount, filled_taker_amount) = get_transfer_amounts(
+            &ctx.accounts.input_mint, &ctx.accounts.input_token_program, leg.input_amount, transfer_fee_mode
+        )?;
+        match &ctx.accounts.taker_input_mint_token_account {
+            None => {
+                require!(is_native_mint(&ctx.accounts.input_mint.key()), BebopError::InvalidNativeTokenAddress);
+
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.taker.to_account_info(),
+                            to: maker_input_mint_token_account.clone(),
+                        },
+                    ),
+                    filled_taker_amount,
+                )?;
+                token_interface::sync_native(CpiContext::new(
+                    ctx.accounts.input_token_program.to_account_info(),
+                    token_interface::SyncNative {
+                        account: maker_input_mint_token_account.clone(),
+                    },
+                ))?;
+            }
+            Some(taker_input_mint_token_account) => transfer(
+                ctx.accounts.input_token_program.to_account_info(),
+                taker_input_mint_token_account.to_account_info(),
+                maker_input_mint_token_account.clone(),
+                ctx.accounts.taker.to_account_info(),
+                ctx.accounts.input_mint.to_account_info(),
+                sent_taker_amount,
+                None,
+                transfer_hook_accounts
+            )?,
+        }
+
+        // signer taker always fills the whole leg
+        let filled_maker_amount: u64 = get_filled_maker_amount(output_amount, leg.input_amount, leg.input_amount);
+        require!(filled_maker_amount > 0, BebopError::ZeroMakerAmount);
+        record_maker_risk_fill(
+            maker_risk_state,
+            maker.key,
+            &ctx.accounts.input_mint.key(),
+            &ctx.accounts.output_mint.key(),
+            leg.maker_risk_state_bump,
+            filled_maker_amount,
+            Clock::get()?.slot,
+        )?;
+        let fee_amount: u64 = get_fee_amount(filled_maker_amount, fee_bps);
+        let (receiver_amount, received_receiver_amount) = get_transfer_amounts(
+            &ctx.accounts.output_mint, &ctx.accounts.output_token_program, filled_maker_amount - fee_amount, transfer_fee_mode
+        )?;
+        let (fee_amount, received_fee_amount) = get_transfer_amounts(
+            &ctx.accounts.output_mint, &ctx.accounts.output_token_program, fee_amount, transfer_fee_mode
+        )?;
+        transfer(
+            ctx.accounts.output_token_program.to_account_info(),
+            maker_output_mint_token_account.clone(),
+            ctx.accounts.receiver_output_mint_token_account.to_account_info(),
+            maker.clone(),
+            ctx.accounts.output_mint.to_account_info(),
+            receiver_amount,
+            None,
+            transfer_hook_accounts
+        )?;
+        if fee_amount > 0 {
+            let fee_recipient_token_account = ctx.accounts.fee_recipient_token_account
+                .as_ref()
+                .ok_or(BebopError::MissingFeeRecipient)?;
+            transfer(
+                ctx.accounts.output_token_program.to_account_info(),
+                maker_output_mint_token_account.clone(),
+                fee_recipient_token_account.to_account_info(),
+                maker.clone(),
+                ctx.accounts.output_mint.to_account_info(),
+                fee_amount,
+                None,
+                transfer_hook_accounts
+            )?;
+        }
+        total_fee_amount += received_fee_amount;
+        fills.push(BatchFill {
+            maker_address: maker.key(),
+            filled_taker_amount,
+            filled_maker_amount: received_receiver_amount + received_fee_amount,
+            fee_amount: received_fee_amount,
+        });
+    }
+    emit_cpi!(BebopSwapBatch{
+        version: EVENT_VERSION,
+        event_id,
+        taker_token: ctx.accounts.input_mint.key(),
+        maker_token: ctx.accounts.output_mint.key(),
+        fills,
+        fee_amount: total_fee_amount,
+    });
+    Ok(())
+}
+
+fn check_maker_token_account<'info>(
+    token_account: &'info AccountInfo<'info>,
+    maker: &Pubkey,
+    mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    require_keys_eq!(*token_account.owner, token_program.key(), ErrorCode::ConstraintTokenTokenProgram);
+    let token_account = InterfaceAccount::<TokenAccount>::try_from(token_account)?;
+    require_keys_eq!(token_account.owner, *maker, ErrorCode::ConstraintTokenOwner);
+    require_keys_eq!(token_account.mint, mint.key(), ErrorCode::ConstraintTokenMint);
+    Ok(())
+}
+
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SwapBatch<'info> {
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    /// CHECK: can be same as taker address or any other address 
+    pub receiver: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        token::authority = taker,
+        token::mint = input_mint,
+        token::token_program = input_token_program
+    )]
+    pub taker_input_mint_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::authority = receiver,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub receiver_output_mint_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Required only when fee_bps > 0
+    #[account(
+        mut,
+        token::authority = config.fee_recipient,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub fee_recipient_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: Validated by token account mint check
+    pub input_mint: UncheckedAccount<'info>,
+    pub input_token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Validated by token account mint check
+    pub output_mint: UncheckedAccount<'info>,
+    pub output_token_program: Interface<'info, TokenInterface>,
+    /// Pays for maker nonce bitmaps on first use
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    // remaining accounts, BATCH_LEG_ACCOUNTS per leg:
+    // maker (Signer), maker_input_mint_token_account, maker_output_mint_token_account, nonce_bitmap, maker_risk_state
+    // followed by transfer-hook accounts of input and output mints
+}
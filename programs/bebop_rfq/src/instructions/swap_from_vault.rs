@@ -0,0 +1,262 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface};
+use crate::{
+    bebop_rfq::{AmountWithExpiry, PdaBumps, PositiveSlippage, SharedAccountSeeds, TransferFeeMode},
+    error::BebopError,
+    events::{BebopSwap, TransferKind, EVENT_VERSION},
+    instructions::{
+        maker_risk_state::record_maker_risk_fill,
+        utils::{
+            get_fee_amount, get_filled_maker_amount, get_filled_taker_amount, get_output_amount, get_positive_slippage_amount, get_transfer_amounts,
+            is_native_mint, transfer, BPS_DENOMINATOR,
+        },
+    },
+    state::{Config, MakerVault, NonceBitmap, CONFIG, MAKER_VAULT, NONCE_BITMAP},
+    SHARED_ACCOUNT,
+};
+
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_swap_from_vault<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, SwapFromVault<'info>>,
+    input_amount: u64,
+    output_amounts: Vec<AmountWithExpiry>,
+    event_id: u64,
+    fee_bps: u16,
+    nonce: u64,
+    transfer_fee_mode: TransferFeeMode,
+    bumps: PdaBumps,
+    shared_account: Option<SharedAccountSeeds>,
+    positive_slippage: Option<PositiveSlippage>,
+) -> Result<()> {
+    require!(!ctx.accounts.config.paused, BebopError::ProgramPaused);
+    let nonce_bitmap = &mut ctx.accounts.nonce_bitmap;
+    nonce_bitmap.owner = ctx.accounts.maker.key();
+    nonce_bitmap.word_pos = NonceBitmap::word_pos(nonce);
+    nonce_bitmap.bump = ctx.bumps.nonce_bitmap;
+    nonce_bitmap.use_nonce(nonce)?;
+    require!((fee_bps as u64) < BPS_DENOMINATOR, BebopError::InvalidFeeBps);
+
+    let (output_tier, output_amount) = get_output_amount(&output_amounts)?;
+    let shared_account = shared_account.unwrap_or_default();
+    let route_id = shared_account.route_id.to_le_bytes();
+    let binding: [&[&[u8]]; 1] = [&[SHARED_ACCOUNT, shared_account.owner.as_ref(), &route_id, &[bumps.shared_account]]];
+    let pda_seeds: Option<&[&[&[u8]]]> = Some(&binding);
+    let filled_taker_amount = get_filled_taker_amount(
+        &ctx.accounts.taker,
+        ctx.accounts.taker_input_mint_token_account.as_deref(),
+        input_amount,
+        binding[0],
+        ctx.accounts.shared_account_owner.as_ref(),
+    )?;
+    // shared-pda account can only send its whole balance
+    let (sent_taker_amount, received_taker_amount) = get_transfer_amounts(
+        &ctx.accounts.input_mint,
+        &ctx.accounts.input_token_program,
+        filled_taker_amount,
+        if ctx.accounts.taker.is_signer { transfer_fee_mode } else { TransferFeeMode::Gross },
+    )?;
+
+    // taker side goes into maker's input mint vault
+    match &ctx.accounts.taker_input_mint_token_account {
+        None => {
+            require!(is_native_mint(&ctx.accounts.input_mint.key()), BebopError::InvalidNativeTokenAddress);
+
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.taker.to_account_info(),
+                        to: ctx.accounts.maker_input_vault_token_account.to_account_info(),
+                    },
+                ),
+                filled_taker_amount,
+            )?;
+            token_interface::sync_native(CpiContext::new(
+                ctx.accounts.input_token_program.to_account_info(),
+                token_interface::SyncNative {
+                    account: ctx.accounts.maker_input_vault_token_account.to_account_info(),
+                },
+            ))?;
+        }
+        Some(taker_input_mint_token_account) => transfer(
+            ctx.accounts.input_token_program.to_account_info(),
+            taker_input_mint_token_account.to_account_info(),
+            ctx.accounts.maker_input_vault_token_account.to_account_info(),
+            ctx.accounts.taker.to_account_info(),
+            ctx.accounts.input_mint.to_account_info(),
+            sent_taker_amount,
+            if ctx.accounts.taker.is_signer {None} else {pda_seeds},
+            ctx.remaining_accounts
+        )?,
+    }
+
+    let filled_taker_amount = received_taker_amount;
+    let quoted_taker_amount = match transfer_fee_mode {
+        TransferFeeMode::Gross => sent_taker_amount,
+        TransferFeeMode::Net => received_taker_amount,
+    };
+    let positive_slippage_output_amount = get_positive_slippage_amount(
+        output_amount.amount,
+        quoted_taker_amount,
+        input_amount,
+        positive_slippage.as_ref(),
+    )?;
+    let filled_maker_amount: u64 = get_filled_maker_amount(output_amount.amount, quoted_taker_amount, input_amount)
+        .saturating_add(positive_slippage_output_amount);
+    require!(filled_maker_amount > 0, BebopError::ZeroMakerAmount);
+    record_maker_risk_fill(
+        &ctx.accounts.maker_risk_state,
+        &ctx.accounts.maker.key(),
+        &ctx.accounts.input_mint.key(),
+        &ctx.accounts.output_mint.key(),
+        bumps.maker_risk_state,
+        filled_maker_amount,
+        Clock::get()?.slot,
+    )?;
+    let fee_amount: u64 = get_fee_amount(filled_maker_amount, fee_bps);
+    let (receiver_amount, received_receiver_amount) = get_transfer_amounts(
+        &ctx.accounts.output_mint,
+        &ctx.accounts.output_token_program,
+        filled_maker_amount - fee_amount,
+        transfer_fee_mode,
+    )?;
+    let (fee_amount, received_fee_amount) = get_transfer_amounts(
+        &ctx.accounts.output_mint,
+        &ctx.accounts.output_token_program,
+        fee_amount,
+        transfer_fee_mode,
+    )?;
+
+    // maker side is signed by the output mint vault
+    let maker = ctx.accounts.maker.key();
+    let output_mint = ctx.accounts.output_mint.key();
+    let vault_binding: [&[&[u8]]; 1] = [&[MAKER_VAULT, maker.as_ref(), output_mint.as_ref(), &[ctx.accounts.maker_output_vault.bump]]];
+    transfer(
+        ctx.accounts.output_token_program.to_account_info(),
+        ctx.accounts.maker_output_vault_token_account.to_account_info(),
+        ctx.accounts.receiver_output_mint_token_account.to_account_info(),
+        ctx.accounts.maker_output_vault.to_account_info(),
+        ctx.accounts.output_mint.to_account_info(),
+        receiver_amount,
+        Some(&vault_binding),
+        ctx.remaining_accounts
+    )?;
+    if fee_amount > 0 {
+        let fee_recipient_token_account = ctx.accounts.fee_recipient_token_account
+            .as_ref()
+            .ok_or(BebopError::MissingFeeRecipient)?;
+        transfer(
+            ctx.accounts.output_token_program.to_account_info(),
+            ctx.accounts.maker_output_vault_token_account.to_account_info(),
+            fee_recipient_token_account.to_account_info(),
+            ctx.accounts.maker_output_vault.to_account_info(),
+            ctx.accounts.output_mint.to_account_info(),
+            fee_amount,
+            Some(&vault_binding),
+            ctx.remaining_accounts
+        )?;
+    }
+    emit_cpi!(BebopSwap{
+        version: EVENT_VERSION,
+        event_id,
+        maker_address: maker,
+        taker_token: ctx.accounts.input_mint.key(),
+        maker_token: output_mint,
+        filled_taker_amount,
+        filled_maker_amount: received_receiver_amount + received_fee_amount,
+        fee_amount: received_fee_amount,
+        taker: ctx.accounts.taker.key(),
+        receiver: ctx.accounts.receiver.key(),
+        input_amount,
+        output_tier,
+        output_amount,
+        partial_fill: quoted_taker_amount < input_amount,
+        input_transfer: TransferKind::new(ctx.accounts.taker_input_mint_token_account.is_some(), true),
+        output_transfer: TransferKind::Token,
+        shared_account: (!ctx.accounts.taker.is_signer).then_some(shared_account),
+        positive_slippage_amount: quoted_taker_amount.saturating_sub(input_amount),
+        positive_slippage_output_amount,
+        relayer_fee_amount: 0,
+    });
+    Ok(())
+}
+
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(input_amount: u64, output_amounts: Vec<AmountWithExpiry>, event_id: u64, fee_bps: u16, nonce: u64)]
+pub struct SwapFromVault<'info> {
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: taker isn't Signer when it's shared-pda account
+    #[account(mut)]
+    pub taker: UncheckedAccount<'info>,
+    /// CHECK: owner of the vaults, doesn't sign
+    pub maker: UncheckedAccount<'info>,
+    /// Hot key authorized by the maker on the output mint vault
+    pub operator: Signer<'info>,
+    /// CHECK: can be same as taker address or any other address
+    pub receiver: UncheckedAccount<'info>,
+    #[account(
+        seeds = [MAKER_VAULT, maker.key().as_ref(), input_mint.key().as_ref()],
+        bump = maker_input_vault.bump,
+    )]
+    pub maker_input_vault: Box<Account<'info, MakerVault>>,
+    #[account(
+        seeds = [MAKER_VAULT, maker.key().as_ref(), output_mint.key().as_ref()],
+        bump = maker_output_vault.bump,
+        constraint = maker_output_vault.operator == operator.key() @ BebopError::InvalidVaultOperator,
+    )]
+    pub maker_output_vault: Box<Account<'info, MakerVault>>,
+    #[account(
+        mut,
+        token::authority = taker,
+        token::mint = input_mint,
+        token::token_program = input_token_program
+    )]
+    pub taker_input_mint_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(mut, address = maker_input_vault.token_account, token::token_program = input_token_program)]
+    pub maker_input_vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = receiver,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub receiver_output_mint_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = maker_output_vault.token_account, token::token_program = output_token_program)]
+    pub maker_output_vault_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Required only when fee_bps > 0
+    #[account(
+        mut,
+        token::authority = config.fee_recipient,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub fee_recipient_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + NonceBitmap::INIT_SPACE,
+        seeds = [NONCE_BITMAP, maker.key().as_ref(), &NonceBitmap::word_pos(nonce).to_le_bytes()],
+        bump,
+    )]
+    pub nonce_bitmap: Box<Account<'info, NonceBitmap>>,
+    /// CHECK: maker's `MakerRiskState` PDA of the mint pair, uninitialized when the maker has no cap.
+    /// Checked by `record_maker_risk_fill`
+    #[account(mut)]
+    pub maker_risk_state: UncheckedAccount<'info>,
+    /// CHECK: Validated by vault seeds
+    pub input_mint: UncheckedAccount<'info>,
+    pub input_token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Validated by vault seeds
+    pub output_mint: UncheckedAccount<'info>,
+    pub output_token_program: Interface<'info, TokenInterface>,
+    /// Pays for the maker nonce bitmap on first use
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Owner of the `shared-account` PDA taker, required when the taker doesn't sign
+    pub shared_account_owner: Option<Signer<'info>>,
+}
@@ -0,0 +1,275 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions::{self as instructions_sysvar, load_current_index_checked, load_instruction_at_checked},
+    system_program,
+};
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface};
+use crate::{
+    bebop_rfq::{Order, PdaBumps, SharedAccountSeeds, TransferFeeMode},
+    error::BebopError,
+    events::{BebopSwap, TransferKind, EVENT_VERSION},
+    instructions::{
+        maker_risk_state::record_maker_risk_fill,
+        utils::{
+            get_fee_amount, get_filled_maker_amount, get_filled_taker_amount, get_output_amount, get_positive_slippage_amount, get_transfer_amounts, is_native_mint,
+            transfer, verify_ed25519_instruction, BPS_DENOMINATOR, MAKER_AUTHORITY,
+        },
+    },
+    state::{Config, NonceBitmap, CONFIG, NONCE_BITMAP},
+    SHARED_ACCOUNT,
+};
+
+
+pub fn handle_swap_signed<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, SwapSigned<'info>>,
+    order: Order,
+    event_id: u64,
+    bumps: PdaBumps,
+    shared_account: Option<SharedAccountSeeds>,
+) -> Result<()> {
+    require!(!ctx.accounts.config.paused, BebopError::ProgramPaused);
+    require!(
+        order.maker == ctx.accounts.maker.key()
+            && order.taker == ctx.accounts.taker.key()
+            && order.receiver == ctx.accounts.receiver.key()
+            && order.input_mint == ctx.accounts.input_mint.key()
+            && order.output_mint == ctx.accounts.output_mint.key(),
+        BebopError::OrderAccountsMismatch
+    );
+
+    // maker signature is verified by Ed25519 program in the previous instruction
+    let current_index = load_current_index_checked(&ctx.accounts.instructions_sysvar)? as usize;
+    require!(current_index > 0, BebopError::InvalidEd25519Instruction);
+    let ed25519_instruction = load_instruction_at_checked(current_index - 1, &ctx.accounts.instructions_sysvar)?;
+    verify_ed25519_instruction(&ed25519_instruction, &order.maker, &order.hash())?;
+
+    let nonce_bitmap = &mut ctx.accounts.nonce_bitmap;
+    nonce_bitmap.owner = order.maker;
+    nonce_bitmap.word_pos = NonceBitmap::word_pos(order.nonce);
+    nonce_bitmap.bump = ctx.bumps.nonce_bitmap;
+    nonce_bitmap.use_nonce(order.nonce)?;
+    require!((order.fee_bps as u64) < BPS_DENOMINATOR, BebopError::InvalidFeeBps);
+
+    let (output_tier, output_amount) = get_output_amount(&order.output_amounts)?;
+    let shared_account = shared_account.unwrap_or_default();
+    let route_id = shared_account.route_id.to_le_bytes();
+    let binding: [&[&[u8]]; 1] = [&[SHARED_ACCOUNT, shared_account.owner.as_ref(), &route_id, &[bumps.shared_account]]];
+    let pda_seeds: Option<&[&[&[u8]]]> = Some(&binding);
+    let filled_taker_amount = get_filled_taker_amount(
+        &ctx.accounts.taker,
+        ctx.accounts.taker_input_mint_token_account.as_deref(),
+        order.input_amount,
+        binding[0],
+        ctx.accounts.shared_account_owner.as_ref(),
+    )?;
+    // shared-pda account can only send its whole balance
+    let (sent_taker_amount, received_taker_amount) = get_transfer_amounts(
+        &ctx.accounts.input_mint,
+        &ctx.accounts.input_token_program,
+        filled_taker_amount,
+        if ctx.accounts.taker.is_signer { order.transfer_fee_mode } else { TransferFeeMode::Gross },
+    )?;
+
+    match &ctx.accounts.taker_input_mint_token_account {
+        None => {
+            require!(is_native_mint(&ctx.accounts.input_mint.key()), BebopError::InvalidNativeTokenAddress);
+
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.taker.to_account_info(),
+                        to: ctx.accounts.maker_input_mint_token_account.to_account_info(),
+                    },
+                ),
+                filled_taker_amount,
+            )?;
+            token_interface::sync_native(CpiContext::new(
+                ctx.accounts.input_token_program.to_account_info(),
+                token_interface::SyncNative {
+                    account: ctx.accounts.maker_input_mint_token_account.to_account_info(),
+                },
+            ))?;
+        }
+        Some(taker_input_mint_token_account) => transfer(
+            ctx.accounts.input_token_program.to_account_info(),
+            taker_input_mint_token_account.to_account_info(),
+            ctx.accounts.maker_input_mint_token_account.to_account_info(),
+            ctx.accounts.taker.to_account_info(),
+            ctx.accounts.input_mint.to_account_info(),
+            sent_taker_amount,
+            if ctx.accounts.taker.is_signer {None} else {pda_seeds},
+            ctx.remaining_accounts
+        )?,
+    }
+
+    let filled_taker_amount = received_taker_amount;
+    let quoted_taker_amount = match order.transfer_fee_mode {
+        TransferFeeMode::Gross => sent_taker_amount,
+        TransferFeeMode::Net => received_taker_amount,
+    };
+    let positive_slippage_output_amount = get_positive_slippage_amount(
+        output_amount.amount,
+        quoted_taker_amount,
+        order.input_amount,
+        order.positive_slippage.as_ref(),
+    )?;
+    let filled_maker_amount: u64 = get_filled_maker_amount(output_amount.amount, quoted_taker_amount, order.input_amount)
+        .saturating_add(positive_slippage_output_amount);
+    require!(filled_maker_amount > 0, BebopError::ZeroMakerAmount);
+    record_maker_risk_fill(
+        &ctx.accounts.maker_risk_state,
+        &ctx.accounts.maker.key(),
+        &ctx.accounts.input_mint.key(),
+        &ctx.accounts.output_mint.key(),
+        bumps.maker_risk_state,
+        filled_maker_amount,
+        Clock::get()?.slot,
+    )?;
+    let fee_amount: u64 = get_fee_amount(filled_maker_amount, order.fee_bps);
+    let (receiver_amount, received_receiver_amount) = get_transfer_amounts(
+        &ctx.accounts.output_mint,
+        &ctx.accounts.output_token_program,
+        filled_maker_amount - fee_amount,
+        order.transfer_fee_mode,
+    )?;
+    let (fee_amount, received_fee_amount) = get_transfer_amounts(
+        &ctx.accounts.output_mint,
+        &ctx.accounts.output_token_program,
+        fee_amount,
+        order.transfer_fee_mode,
+    )?;
+
+    // maker side is moved by the program as a delegate of maker token account
+    let maker_authority_binding: [&[&[u8]]; 1] = [&[MAKER_AUTHORITY, &[ctx.bumps.maker_authority]]];
+    transfer(
+        ctx.accounts.output_token_program.to_account_info(),
+        ctx.accounts.maker_output_mint_token_account.to_account_info(),
+        ctx.accounts.receiver_output_mint_token_account.to_account_info(),
+        ctx.accounts.maker_authority.to_account_info(),
+        ctx.accounts.output_mint.to_account_info(),
+        receiver_amount,
+        Some(&maker_authority_binding),
+        ctx.remaining_accounts
+    )?;
+    if fee_amount > 0 {
+        let fee_recipient_token_account = ctx.accounts.fee_recipient_token_account
+            .as_ref()
+            .ok_or(BebopError::MissingFeeRecipient)?;
+        transfer(
+            ctx.accounts.output_token_program.to_account_info(),
+            ctx.accounts.maker_output_mint_token_account.to_account_info(),
+            fee_recipient_token_account.to_account_info(),
+            ctx.accounts.maker_authority.to_account_info(),
+            ctx.accounts.output_mint.to_account_info(),
+            fee_amount,
+            Some(&maker_authority_binding),
+            ctx.remaining_accounts
+        )?;
+    }
+    emit_cpi!(BebopSwap{
+        version: EVENT_VERSION,
+        event_id,
+        maker_address: order.maker,
+        taker_token: order.input_mint,
+        maker_token: order.output_mint,
+        filled_taker_amount,
+        filled_maker_amount: received_receiver_amount + received_fee_amount,
+        fee_amount: received_fee_amount,
+        taker: order.taker,
+        receiver: order.receiver,
+        input_amount: order.input_amount,
+        output_tier,
+        output_amount,
+        partial_fill: quoted_taker_amount < order.input_amount,
+        input_transfer: TransferKind::new(ctx.accounts.taker_input_mint_token_account.is_some(), true),
+        output_transfer: TransferKind::Token,
+        shared_account: (!ctx.accounts.taker.is_signer).then_some(shared_account),
+        positive_slippage_amount: quoted_taker_amount.saturating_sub(order.input_amount),
+        positive_slippage_output_amount,
+        relayer_fee_amount: 0,
+    });
+    Ok(())
+}
+
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(order: Order)]
+pub struct SwapSigned<'info> {
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: taker isn't Signer when it's shared-pda account 
+    #[account(mut)]
+    pub taker: UncheckedAccount<'info>,
+    /// CHECK: maker authorizes the order with Ed25519 signature
+    pub maker: UncheckedAccount<'info>,
+    /// CHECK: can be same as taker address or any other address 
+    pub receiver: UncheckedAccount<'info>,
+    /// CHECK: delegate of maker token accounts
+    #[account(seeds = [MAKER_AUTHORITY], bump)]
+    pub maker_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        token::authority = taker,
+        token::mint = input_mint,
+        token::token_program = input_token_program
+    )]
+    pub taker_input_mint_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        token::authority = maker,
+        token::mint = input_mint,
+        token::token_program = input_token_program
+    )]
+    pub maker_input_mint_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = receiver,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub receiver_output_mint_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = maker,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub maker_output_mint_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Required only when order fee_bps > 0
+    #[account(
+        mut,
+        token::authority = config.fee_recipient,
+        token::mint = output_mint,
+        token::token_program = output_token_program
+    )]
+    pub fee_recipient_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + NonceBitmap::INIT_SPACE,
+        seeds = [NONCE_BITMAP, maker.key().as_ref(), &NonceBitmap::word_pos(order.nonce).to_le_bytes()],
+        bump,
+    )]
+    pub nonce_bitmap: Box<Account<'info, NonceBitmap>>,
+    /// CHECK: maker's `MakerRiskState` PDA of the mint pair, uninitialized when the maker has no cap.
+    /// Checked by `record_maker_risk_fill`
+    #[account(mut)]
+    pub maker_risk_state: UncheckedAccount<'info>,
+    /// CHECK: Validated by token account mint check
+    pub input_mint: UncheckedAccount<'info>,
+    pub input_token_program: Interface<'info, TokenInterface>,
+    /// CHECK: Validated by token account mint check
+    pub output_mint: UncheckedAccount<'info>,
+    pub output_token_program: Interface<'info, TokenInterface>,
+    /// CHECK: instructions sysvar
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    /// Pays for the maker nonce bitmap on first use
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Owner of the `shared-account` PDA taker, required when the taker doesn't sign
+    pub shared_account_owner: Option<Signer<'info>>,
+}
@@ -0,0 +1,98 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token_interface::{self, TokenAccount, TokenInterface};
+
+use crate::{error::BebopError, instructions::utils::transfer, SHARED_ACCOUNT};
+
+
+pub fn handle_sweep_shared<'c: 'info, 'info>(
+    ctx: Context<'_, '_, 'c, 'info, SweepShared<'info>>,
+    route_id: u64,
+    shared_account_bump: u8,
+    close_token_account: bool,
+) -> Result<()> {
+    let taker = ctx.accounts.taker.key();
+    let route_id = route_id.to_le_bytes();
+    let binding: [&[&[u8]]; 1] = [&[SHARED_ACCOUNT, taker.as_ref(), &route_id, &[shared_account_bump]]];
+
+    match (
+        &ctx.accounts.shared_token_account,
+        &ctx.accounts.taker_token_account,
+        &ctx.accounts.mint,
+        &ctx.accounts.token_program,
+    ) {
+        (Some(shared_token_account), Some(taker_token_account), Some(mint), Some(token_program)) => {
+            require!(
+                shared_token_account.mint == mint.key()
+                    && taker_token_account.mint == mint.key()
+                    && *shared_token_account.to_account_info().owner == token_program.key()
+                    && *taker_token_account.to_account_info().owner == token_program.key(),
+                BebopError::InvalidSweepAccounts
+            );
+            if shared_token_account.amount > 0 {
+                transfer(
+                    token_program.to_account_info(),
+                    shared_token_account.to_account_info(),
+                    taker_token_account.to_account_info(),
+                    ctx.accounts.shared_account.to_account_info(),
+                    mint.to_account_info(),
+                    shared_token_account.amount,
+                    Some(&binding),
+                    ctx.remaining_accounts,
+                )?;
+            }
+            // rent goes back to the taker, the route recreates the account when needed
+            if close_token_account {
+                token_interface::close_account(CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    token_interface::CloseAccount {
+                        account: shared_token_account.to_account_info(),
+                        destination: ctx.accounts.taker.to_account_info(),
+                        authority: ctx.accounts.shared_account.to_account_info(),
+                    },
+                    &binding,
+                ))?;
+            }
+        }
+        (None, None, None, None) => {}
+        _ => return err!(BebopError::InvalidSweepAccounts),
+    }
+
+    // shared-pda account stays rent-exempt
+    let lamports = ctx.accounts.shared_account.lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(0));
+    if lamports > 0 {
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.shared_account.to_account_info(),
+                    to: ctx.accounts.taker.to_account_info(),
+                },
+                &binding,
+            ),
+            lamports,
+        )?;
+    }
+    Ok(())
+}
+
+
+#[derive(Accounts)]
+#[instruction(route_id: u64, shared_account_bump: u8)]
+pub struct SweepShared<'info> {
+    /// Owner of the shared-pda account, receives the residual balance
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    /// CHECK: taker's shared-pda account, address checked with caller supplied bump
+    #[account(mut, seeds = [SHARED_ACCOUNT, taker.key().as_ref(), &route_id.to_le_bytes()], bump = shared_account_bump)]
+    pub shared_account: UncheckedAccount<'info>,
+    /// Token sweep accounts, all four are None for a lamports-only sweep
+    #[account(mut, token::authority = shared_account)]
+    pub shared_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(mut, token::authority = taker)]
+    pub taker_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: Validated by token account mint check
+    pub mint: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
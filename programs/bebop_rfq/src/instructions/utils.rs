@@ -1,4 +1,8 @@
-use anchor_lang::{prelude::*, solana_program::program_pack::Pack, system_program};
+use anchor_lang::{
+    prelude::*,
+    solana_program::{ed25519_program, instruction::Instruction, program_pack::Pack},
+    system_program,
+};
 use anchor_spl::{
     associated_token::spl_associated_token_account::tools::account::create_pda_account,
     token::{
@@ -8,18 +12,218 @@ use anchor_spl::{
     token_2022::spl_token_2022::{
         self,
         extension::{
-            transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+            transfer_fee::TransferFeeConfig, transfer_hook, BaseStateWithExtensions, ExtensionType, StateWithExtensions,
         },
+        state::AccountState,
     },
-    token_interface::{self, spl_pod::primitives::PodU16, TokenInterface},
+    token_interface::{self, TokenAccount},
 };
 
-use crate::error::BebopError;
+use crate::{
+    bebop_rfq::{AmountWithExpiry, PositiveSlippage, TransferFeeMode},
+    error::BebopError,
+    instructions::debit_maker_wsol_vault,
+    state::MakerWsolVault,
+};
 
 pub const TEMPORARY_WSOL_TOKEN_ACCOUNT: &[u8] = b"temporary-wsol-token-account";
 pub const SHARED_ACCOUNT: &[u8] = b"shared-account";
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const MAKER_AUTHORITY: &[u8] = b"maker-authority";
+pub const TAKER_AUTHORITY: &[u8] = b"taker-authority";
+
+const ED25519_SIGNATURE_OFFSETS_START: usize = 2;
+const ED25519_SIGNATURE_OFFSETS_SIZE: usize = 14;
+/// `ExtensionType::Pausable`, not known to spl-token-2022 version we build with
+const PAUSABLE_EXTENSION_TYPE: u16 = 26;
+
 
+/// Picks the first non-expired amount, amounts must decrease while expiries increase.
+/// Returns index of the selected tier with the tier itself
+pub fn get_output_amount(output_amounts: &[AmountWithExpiry]) -> Result<(u8, AmountWithExpiry)> {
+    let now = Clock::get()?.unix_timestamp as u64;
+    let mut selected: Option<(u8, AmountWithExpiry)> = None;
+    for (i, amount_with_expiry) in output_amounts.iter().enumerate() {
+        require!(
+            i == 0 || 
+            (amount_with_expiry.amount <= output_amounts[i - 1].amount && amount_with_expiry.expiry > output_amounts[i - 1].expiry),
+            BebopError::InvalidOutputAmount
+        );
+        if amount_with_expiry.expiry >= now {
+            selected = Some((i as u8, *amount_with_expiry));
+            break;
+        }
+    }
+    match selected {
+        Some((tier, amount_with_expiry)) if amount_with_expiry.amount > 0 => Ok((tier, amount_with_expiry)),
+        _ => err!(BebopError::OrderExpired),
+    }
+}
 
+/// Returns taker amount. When taker is shared-pda account whole balance is used,
+/// its address is checked against caller supplied `shared_account_seeds` (with bump)
+/// and the owner in the seeds must sign as `shared_account_owner`
+pub fn get_filled_taker_amount<'info>(
+    taker: &AccountInfo<'info>,
+    taker_input_mint_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    input_amount: u64,
+    shared_account_seeds: &[&[u8]],
+    shared_account_owner: Option<&Signer<'info>>,
+) -> Result<u64> {
+    let filled_taker_amount = if !taker.is_signer {
+        let expected_pda_address = Pubkey::create_program_address(shared_account_seeds, &crate::ID)
+            .map_err(|_| BebopError::WrongSharedAccountAddress)?;
+        require_keys_eq!(taker.key(), expected_pda_address, BebopError::WrongSharedAccountAddress);
+        require!(
+            shared_account_owner.is_some_and(|owner| owner.key().as_ref() == shared_account_seeds[1]),
+            BebopError::SharedAccountOwnerNotSigner
+        );
+        match taker_input_mint_token_account {
+            Some(token_acc) => token_acc.amount,
+            None => taker.lamports(),
+        }
+    } else {
+        input_amount
+    };
+    require!(filled_taker_amount > 0, BebopError::ZeroTakerAmount);
+    Ok(filled_taker_amount)
+}
+
+pub fn get_filled_maker_amount(output_amount: u64, filled_taker_amount: u64, input_amount: u64) -> u64 {
+    if filled_taker_amount < input_amount {
+        ((output_amount as u128 * filled_taker_amount as u128) / input_amount as u128) as u64
+    } else {
+        // max maker_amount is output_amount, even if filled_taker_amount > input_amount
+        output_amount
+    }
+}
+
+/// Extra maker output for the taker share of `filled_taker_amount` above `input_amount`, priced at the quote rate.
+/// Scaled maker output is capped at `max_output_amount`
+pub fn get_positive_slippage_amount(
+    output_amount: u64,
+    filled_taker_amount: u64,
+    input_amount: u64,
+    positive_slippage: Option<&PositiveSlippage>,
+) -> Result<u64> {
+    let Some(positive_slippage) = positive_slippage else {
+        return Ok(0);
+    };
+    require!((positive_slippage.taker_share_bps as u64) <= BPS_DENOMINATOR, BebopError::InvalidSlippageShareBps);
+    if filled_taker_amount <= input_amount {
+        return Ok(0);
+    }
+    let taker_surplus = (filled_taker_amount - input_amount) as u128 * positive_slippage.taker_share_bps as u128
+        / BPS_DENOMINATOR as u128;
+    let slippage_amount = (output_amount as u128 * taker_surplus / input_amount as u128).min(u64::MAX as u128) as u64;
+    Ok(match positive_slippage.max_output_amount {
+        Some(max_output_amount) => slippage_amount.min(max_output_amount.saturating_sub(output_amount)),
+        None => slippage_amount,
+    })
+}
+
+pub fn get_fee_amount(amount: u64, fee_bps: u16) -> u64 {
+    ((amount as u128 * fee_bps as u128) / BPS_DENOMINATOR as u128) as u64
+}
+
+
+/// Checks that `instruction` is an Ed25519 program instruction with a single signature
+/// of `signer` over `message`, all data must be inlined into that instruction
+pub fn verify_ed25519_instruction(instruction: &Instruction, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    require_keys_eq!(instruction.program_id, ed25519_program::ID, BebopError::InvalidEd25519Instruction);
+    require!(instruction.accounts.is_empty(), BebopError::InvalidEd25519Instruction);
+    let data = &instruction.data;
+    require!(
+        data.len() >= ED25519_SIGNATURE_OFFSETS_START + ED25519_SIGNATURE_OFFSETS_SIZE && data[0] == 1,
+        BebopError::InvalidEd25519Instruction
+    );
+    let read_u16 = |i: usize| {
+        let offset = ED25519_SIGNATURE_OFFSETS_START + 2 * i;
+        u16::from_le_bytes([data[offset], data[offset + 1]])
+    };
+    // signature, public key and message must come from the Ed25519 instruction itself
+    require!(
+        read_u16(1) == u16::MAX && read_u16(3) == u16::MAX && read_u16(6) == u16::MAX,
+        BebopError::InvalidEd25519Instruction
+    );
+    let public_key_offset = read_u16(2) as usize;
+    let message_offset = read_u16(4) as usize;
+    let message_size = read_u16(5) as usize;
+    require!(
+        data.get(public_key_offset..public_key_offset + 32) == Some(signer.as_ref()),
+        BebopError::InvalidOrderSignature
+    );
+    require!(
+        data.get(message_offset..message_offset + message_size) == Some(message),
+        BebopError::InvalidOrderSignature
+    );
+    Ok(())
+}
+
+
+/// Returns (sent, received) amounts of a transfer of `amount` with `mint` transfer fee for the current epoch.
+/// `Gross` - `amount` is sent and receiver gets it minus the fee, `Net` - receiver gets exactly `amount`
+pub fn get_transfer_amounts(
+    mint: &AccountInfo,
+    token_program: &AccountInfo,
+    amount: u64,
+    transfer_fee_mode: TransferFeeMode,
+) -> Result<(u64, u64)> {
+    if !token_program.key.eq(&spl_token_2022::ID) {
+        return Ok((amount, amount));
+    }
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state_with_extensions =
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let Ok(transfer_fee_config) = mint_state_with_extensions.get_extension::<TransferFeeConfig>() else {
+        return Ok((amount, amount));
+    };
+    let epoch = Clock::get()?.epoch;
+    let sent_amount = match transfer_fee_mode {
+        TransferFeeMode::Gross => amount,
+        TransferFeeMode::Net => transfer_fee_config
+            .calculate_inverse_epoch_fee(epoch, amount)
+            .and_then(|fee| amount.checked_add(fee))
+            .ok_or(BebopError::InvalidTransferFee)?,
+    };
+    let fee = transfer_fee_config
+        .calculate_epoch_fee(epoch, sent_amount)
+        .ok_or(BebopError::InvalidTransferFee)?;
+    Ok((sent_amount, sent_amount - fee))
+}
+
+
+/// Extension policy for Token-2022 mints, compiled in.
+/// Extensions that let a third party freeze, pause or claw back maker inventory are rejected, others are allowed
+pub fn check_mint_extensions(mint_data: &[u8]) -> Result<()> {
+    // TLV entries follow base mint padded to account length and account type byte
+    let mut offset = spl_token_2022::state::Account::LEN + 1;
+    while offset + 4 <= mint_data.len() {
+        let extension_type = u16::from_le_bytes([mint_data[offset], mint_data[offset + 1]]);
+        let length = u16::from_le_bytes([mint_data[offset + 2], mint_data[offset + 3]]) as usize;
+        let value = mint_data
+            .get(offset + 4..offset + 4 + length)
+            .ok_or(ProgramError::InvalidAccountData)?;
+        match extension_type {
+            t if t == u16::from(ExtensionType::Uninitialized) => break,
+            t if t == u16::from(ExtensionType::PermanentDelegate) => return err!(BebopError::PermanentDelegateNotAllowed),
+            t if t == u16::from(ExtensionType::NonTransferable) => return err!(BebopError::NonTransferableNotAllowed),
+            t if t == u16::from(ExtensionType::ConfidentialTransferMint) => return err!(BebopError::ConfidentialTransferNotAllowed),
+            t if t == u16::from(ExtensionType::DefaultAccountState) => require!(
+                value.first() != Some(&(AccountState::Frozen as u8)),
+                BebopError::DefaultAccountStateFrozenNotAllowed
+            ),
+            PAUSABLE_EXTENSION_TYPE => return err!(BebopError::PausableNotAllowed),
+            _ => {}
+        }
+        offset += 4 + length;
+    }
+    Ok(())
+}
+
+/// `additional_accounts` must contain transfer-hook program, its `ExtraAccountMetaList` and extra accounts
+/// when the mint has TransferHook extension, they are resolved by address
+#[allow(clippy::too_many_arguments)]
 pub fn transfer<'info>(
     token_program: AccountInfo<'info>,
     from: AccountInfo<'info>,
@@ -28,21 +232,30 @@ pub fn transfer<'info>(
     mint: AccountInfo<'info>,
     amount: u64,
     seeds: Option<&[&[&[u8]]]>,
+    additional_accounts: &[AccountInfo<'info>],
 ) -> Result<()> {
     let decimals_for_transfer_checked = if token_program.key.eq(&spl_token_2022::ID) {
         let mint_data = mint.try_borrow_data()?;
+        check_mint_extensions(&mint_data)?;
         let mint_state_with_extensions =
             StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
-        if let Ok(transfer_fee_config) = mint_state_with_extensions.get_extension::<TransferFeeConfig>(){
-            require!(
-                transfer_fee_config
-                    .get_epoch_fee(Clock::get()?.epoch)
-                    .transfer_fee_basis_points
-                    == PodU16([0; 2]),
-                BebopError::Token2022MintExtensionNotSupported
-            );
+        let decimals = mint_state_with_extensions.base.decimals;
+        if transfer_hook::get_program_id(&mint_state_with_extensions).is_some() {
+            drop(mint_data);
+            spl_token_2022::onchain::invoke_transfer_checked(
+                token_program.key,
+                from,
+                mint,
+                to,
+                authority,
+                additional_accounts,
+                amount,
+                decimals,
+                seeds.unwrap_or_default(),
+            )?;
+            return Ok(());
         }
-        Some(mint_state_with_extensions.base.decimals)
+        Some(decimals)
     } else {
         None
     };
@@ -74,25 +287,57 @@ pub fn transfer<'info>(
 }
 
 
+/// Wrapped SOL mint of either token program
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+
 #[allow(clippy::too_many_arguments)]
 pub fn unwrap_sol<'info>(
     maker: AccountInfo<'info>,
     sender: AccountInfo<'info>,
     sender_token_account: AccountInfo<'info>,
     receiver: Option<AccountInfo<'info>>,
-    temporary_wsol_token_account: Option<&AccountInfo<'info>>,
+    remaining_accounts: &'info [AccountInfo<'info>],
     wsol_mint: AccountInfo<'info>,
     token_program: AccountInfo<'info>,
     system_program: AccountInfo<'info>,
     amount: u64,
+    bump: u8,
 ) -> Result<()> {
-    let temporary_wsol_token_account = temporary_wsol_token_account
+    let temporary_wsol_token_account = remaining_accounts.first()
         .ok_or(BebopError::MissingTemporaryWrappedSolTokenAccount)?;
+    // maker's persistent vault: WSOL goes to its token account, native SOL comes from its lamports
+    if temporary_wsol_token_account.owner == &crate::ID {
+        let maker_wsol_vault: Account<MakerWsolVault> = Account::try_from(temporary_wsol_token_account)
+            .map_err(|_| BebopError::InvalidMakerWsolVault)?;
+        let vault_token_account = remaining_accounts.get(1).ok_or(BebopError::InvalidMakerWsolVault)?;
+        // vault token account holds spl-token WSOL only
+        require!(
+            maker_wsol_vault.maker == maker.key()
+                && maker_wsol_vault.token_account == vault_token_account.key()
+                && wsol_mint.key() == spl_token::native_mint::ID,
+            BebopError::InvalidMakerWsolVault
+        );
+        token::transfer(
+            CpiContext::new(
+                token_program,
+                token::Transfer {
+                    from: sender_token_account,
+                    to: vault_token_account.clone(),
+                    authority: sender,
+                },
+            ),
+            amount,
+        )?;
+        return debit_maker_wsol_vault(temporary_wsol_token_account, &receiver.unwrap_or(maker), amount);
+    }
 
-    let (expected_temporary_wsol_token_account, bump) = Pubkey::find_program_address(
-        &[TEMPORARY_WSOL_TOKEN_ACCOUNT, maker.key.as_ref()],
+    let expected_temporary_wsol_token_account = Pubkey::create_program_address(
+        &[TEMPORARY_WSOL_TOKEN_ACCOUNT, maker.key.as_ref(), &[bump]],
         &crate::ID,
-    );
+    ).map_err(|_| ErrorCode::ConstraintSeeds)?;
     require_keys_eq!(
         temporary_wsol_token_account.key(),
         expected_temporary_wsol_token_account
@@ -102,36 +347,38 @@ pub fn unwrap_sol<'info>(
         &maker,
         &Rent::get()?,
         spl_token::state::Account::LEN,
-        &spl_token::ID,
+        token_program.key,
         &system_program,
         temporary_wsol_token_account,
         new_pda_signer_seeds,
     )?;
-    token::initialize_account3(CpiContext::new(
+    token_interface::initialize_account3(CpiContext::new(
         token_program.to_account_info(),
-        token::InitializeAccount3 {
+        token_interface::InitializeAccount3 {
             account: temporary_wsol_token_account.clone(),
-            mint: wsol_mint,
+            mint: wsol_mint.clone(),
             authority: maker.clone(),
         },
     ))?;
 
-    token::transfer(
+    token_interface::transfer_checked(
         CpiContext::new(
             token_program.clone(),
-            token::Transfer {
+            token_interface::TransferChecked {
                 from: sender_token_account.clone(),
+                mint: wsol_mint,
                 to: temporary_wsol_token_account.clone(),
                 authority: sender.clone(),
             },
         ),
         amount,
+        spl_token::native_mint::DECIMALS,
     )?;
 
     // Close temporary wsol token account into the maker
-    token::close_account(CpiContext::new(
+    token_interface::close_account(CpiContext::new(
         token_program.to_account_info(),
-        token::CloseAccount {
+        token_interface::CloseAccount {
             account: temporary_wsol_token_account.clone(),
             destination: maker.clone(),
             authority: maker.clone(),
@@ -1,13 +1,38 @@
 mod instructions;
 pub mod error;
+pub mod events;
+pub mod state;
 
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::hash::hash};
 use instructions::*;
+use state::MAX_DELEGATE_MINTS;
 
 #[constant]
 pub const TEMPORARY_WSOL_TOKEN_ACCOUNT: &[u8] = instructions::TEMPORARY_WSOL_TOKEN_ACCOUNT;
 #[constant]
 pub const SHARED_ACCOUNT: &[u8] = instructions::SHARED_ACCOUNT;
+#[constant]
+pub const CONFIG: &[u8] = state::CONFIG;
+#[constant]
+pub const NONCE_BITMAP: &[u8] = state::NONCE_BITMAP;
+#[constant]
+pub const TAKER_NONCE_BITMAP: &[u8] = state::TAKER_NONCE_BITMAP;
+#[constant]
+pub const OUTPUT_SNAPSHOT: &[u8] = state::OUTPUT_SNAPSHOT;
+#[constant]
+pub const MAKER_AUTHORITY: &[u8] = instructions::MAKER_AUTHORITY;
+#[constant]
+pub const TAKER_AUTHORITY: &[u8] = instructions::TAKER_AUTHORITY;
+#[constant]
+pub const MAKER_DELEGATE: &[u8] = state::MAKER_DELEGATE;
+#[constant]
+pub const MAKER_RISK_STATE: &[u8] = state::MAKER_RISK_STATE;
+#[constant]
+pub const MAKER_VAULT: &[u8] = state::MAKER_VAULT;
+#[constant]
+pub const MAKER_WSOL_VAULT: &[u8] = state::MAKER_WSOL_VAULT;
+
+pub const TAKER_ORDER_DOMAIN: &[u8] = b"taker-order";
 
 
 declare_id!("bbbkLKxMtHnw8tdioevBdg4jzjHrY9wT9GHwjoPMKDN");
@@ -16,18 +41,282 @@ declare_id!("bbbkLKxMtHnw8tdioevBdg4jzjHrY9wT9GHwjoPMKDN");
 pub mod bebop_rfq {
     use super::*;
 
-    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
     pub struct AmountWithExpiry {
         pub amount: u64, 
         pub expiry: u64,
     }
 
+    /// How Token-2022 transfer fees apply to quoted amounts
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+    pub enum TransferFeeMode {
+        /// Amounts are sent, the other side receives them minus the transfer fee
+        #[default]
+        Gross,
+        /// Amounts are received, the sender pays the transfer fee on top
+        Net,
+    }
+
+    /// PDA bumps supplied by the caller, checked with `create_program_address`
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+    pub struct PdaBumps {
+        /// `shared-account` bump of `SharedAccountSeeds`, used when taker is shared-pda account
+        pub shared_account: u8,
+        /// `temporary-wsol-token-account` bump of the maker, used when wrapped SOL is unwrapped
+        pub temporary_wsol_token_account: u8,
+        /// Canonical `maker-risk-state` bump of the maker and mint pair
+        pub maker_risk_state: u8,
+    }
+
+    /// Shared-pda account seeds: `SHARED_ACCOUNT`, owner wallet, little-endian route id.
+    /// Required when taker is shared-pda account
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct SharedAccountSeeds {
+        pub owner: Pubkey,
+        pub route_id: u64,
+    }
+
+    /// Split of shared-pda surplus above `input_amount`: the taker share is priced at the quote rate
+    /// and paid as extra maker output, the rest stays with the maker
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct PositiveSlippage {
+        pub taker_share_bps: u16,
+        /// Ceiling of the scaled up maker output
+        pub max_output_amount: Option<u64>,
+    }
+
+    /// Fee payer reimbursement taken from the receiver's output, `amount` is picked at submission
+    /// and can't exceed `max_amount` agreed by the taker
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct RelayerFee {
+        pub amount: u64,
+        pub max_amount: u64,
+    }
+
+    /// Delegate ceilings of maker output in `mint`, in its units
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq, InitSpace)]
+    pub struct DelegateMintLimit {
+        pub mint: Pubkey,
+        /// Ceiling of maker output per fill
+        pub max_fill_amount: Option<u64>,
+        /// Ceiling of maker output per UTC day, summed over fills of the delegate with this output mint
+        pub max_daily_volume: Option<u64>,
+    }
+
+    /// Scope of a maker delegate
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq, InitSpace)]
+    pub struct DelegateLimits {
+        /// Mints allowed on both sides of a fill, limits of the output mint apply.
+        /// Empty allows any mint without amount limits
+        #[max_len(MAX_DELEGATE_MINTS)]
+        pub mints: Vec<DelegateMintLimit>,
+        /// Last slot the delegate can sign at
+        pub expiry_slot: u64,
+    }
+
+    /// Quote signed off-line by the maker for `swap_signed`
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct Order {
+        pub maker: Pubkey,
+        pub taker: Pubkey,
+        pub receiver: Pubkey,
+        pub input_mint: Pubkey,
+        pub output_mint: Pubkey,
+        pub input_amount: u64,
+        pub output_amounts: Vec<AmountWithExpiry>,
+        pub nonce: u64,
+        pub fee_bps: u16,
+        pub transfer_fee_mode: TransferFeeMode,
+        pub positive_slippage: Option<PositiveSlippage>,
+    }
+
+    /// Quote signed off-line by the taker for gasless `swap`, the executor can rebuild the transaction
+    /// but can't change what the taker agreed to
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct TakerOrder {
+        pub taker: Pubkey,
+        pub receiver: Pubkey,
+        pub maker: Pubkey,
+        pub input_mint: Pubkey,
+        pub output_mint: Pubkey,
+        pub input_amount: u64,
+        pub output_amounts: Vec<AmountWithExpiry>,
+        pub nonce: u64,
+        pub fee_bps: u16,
+        /// Fee recipient token account of the swap, default pubkey when `fee_bps` is zero
+        pub fee_recipient_token_account: Pubkey,
+        pub transfer_fee_mode: TransferFeeMode,
+        /// Zero when the taker doesn't pay the relayer
+        pub max_relayer_fee: u64,
+        /// Fee payer receiving the relayer fee, default pubkey without a relayer fee
+        pub relayer: Pubkey,
+        /// Spent in taker's own `NonceBitmap`, see `cancel_taker_nonces`
+        pub taker_nonce: u64,
+    }
+
+    /// One maker of `swap_batch`
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+    pub struct BatchLeg {
+        pub input_amount: u64,
+        pub output_amounts: Vec<AmountWithExpiry>,
+        pub nonce: u64,
+        /// Bump of the maker `NonceBitmap` of `nonce`, checked with `create_program_address`
+        pub nonce_bitmap_bump: u8,
+        /// Canonical bump of the maker `MakerRiskState` of the pair
+        pub maker_risk_state_bump: u8,
+    }
+
+    impl Order {
+        /// Maker signs sha256 of program id followed by borsh serialized order
+        pub fn hash(&self) -> [u8; 32] {
+            let mut data = crate::ID.to_bytes().to_vec();
+            // writing into Vec can't fail
+            self.serialize(&mut data).unwrap();
+            hash(&data).to_bytes()
+        }
+    }
+
+    impl TakerOrder {
+        /// Taker signs sha256 of program id, `TAKER_ORDER_DOMAIN` and borsh serialized order,
+        /// the domain keeps taker and maker order signatures apart
+        pub fn hash(&self) -> [u8; 32] {
+            let mut data = crate::ID.to_bytes().to_vec();
+            data.extend_from_slice(TAKER_ORDER_DOMAIN);
+            // writing into Vec can't fail
+            self.serialize(&mut data).unwrap();
+            hash(&data).to_bytes()
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn swap<'c: 'info, 'info>(
         ctx: Context<'_, '_, 'c, 'info, Swap<'info>>,
         input_amount: u64,
         output_amounts: Vec<AmountWithExpiry>,
         event_id: u64,
+        fee_bps: u16,
+        nonce: u64,
+        transfer_fee_mode: TransferFeeMode,
+        bumps: PdaBumps,
+        shared_account: Option<SharedAccountSeeds>,
+        positive_slippage: Option<PositiveSlippage>,
+        relayer_fee: Option<RelayerFee>,
+        taker_nonce: Option<u64>,
+    ) -> Result<()> {
+        handle_swap(
+            ctx, input_amount, output_amounts, event_id, fee_bps, nonce, transfer_fee_mode, bumps, shared_account, positive_slippage,
+            relayer_fee, taker_nonce
+        )
+    }
+
+    pub fn swap_signed<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, SwapSigned<'info>>,
+        order: Order,
+        event_id: u64,
+        bumps: PdaBumps,
+        shared_account: Option<SharedAccountSeeds>,
+    ) -> Result<()> {
+        handle_swap_signed(ctx, order, event_id, bumps, shared_account)
+    }
+
+    pub fn swap_batch<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, SwapBatch<'info>>,
+        legs: Vec<BatchLeg>,
+        event_id: u64,
+        fee_bps: u16,
+        transfer_fee_mode: TransferFeeMode,
+    ) -> Result<()> {
+        handle_swap_batch(ctx, legs, event_id, fee_bps, transfer_fee_mode)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap_from_vault<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, SwapFromVault<'info>>,
+        input_amount: u64,
+        output_amounts: Vec<AmountWithExpiry>,
+        event_id: u64,
+        fee_bps: u16,
+        nonce: u64,
+        transfer_fee_mode: TransferFeeMode,
+        bumps: PdaBumps,
+        shared_account: Option<SharedAccountSeeds>,
+        positive_slippage: Option<PositiveSlippage>,
+    ) -> Result<()> {
+        handle_swap_from_vault(
+            ctx, input_amount, output_amounts, event_id, fee_bps, nonce, transfer_fee_mode, bumps, shared_account, positive_slippage
+        )
+    }
+
+    pub fn deposit<'c: 'info, 'info>(ctx: Context<'_, '_, 'c, 'info, Deposit<'info>>, amount: u64) -> Result<()> {
+        handle_deposit(ctx, amount)
+    }
+
+    pub fn withdraw<'c: 'info, 'info>(ctx: Context<'_, '_, 'c, 'info, Withdraw<'info>>, amount: u64) -> Result<()> {
+        handle_withdraw(ctx, amount)
+    }
+
+    pub fn set_vault_operator(ctx: Context<SetVaultOperator>, operator: Pubkey) -> Result<()> {
+        handle_set_vault_operator(ctx, operator)
+    }
+
+    pub fn set_delegate(ctx: Context<SetDelegate>, delegate: Pubkey, limits: DelegateLimits) -> Result<()> {
+        handle_set_delegate(ctx, delegate, limits)
+    }
+
+    pub fn revoke_delegate(ctx: Context<RevokeDelegate>) -> Result<()> {
+        handle_revoke_delegate(ctx)
+    }
+
+    pub fn set_risk_limit(ctx: Context<SetRiskLimit>, window_slots: u64, max_volume: u64) -> Result<()> {
+        handle_set_risk_limit(ctx, window_slots, max_volume)
+    }
+
+    pub fn sweep_shared<'c: 'info, 'info>(
+        ctx: Context<'_, '_, 'c, 'info, SweepShared<'info>>,
+        route_id: u64,
+        shared_account_bump: u8,
+        close_token_account: bool,
     ) -> Result<()> {
-        handle_swap(ctx, input_amount, output_amounts, event_id)
+        handle_sweep_shared(ctx, route_id, shared_account_bump, close_token_account)
+    }
+
+    pub fn init_maker_wsol_vault(ctx: Context<InitMakerWsolVault>, lamports: u64) -> Result<()> {
+        handle_init_maker_wsol_vault(ctx, lamports)
+    }
+
+    pub fn withdraw_maker_wsol_vault(ctx: Context<WithdrawMakerWsolVault>, lamports: u64, wsol_amount: u64) -> Result<()> {
+        handle_withdraw_maker_wsol_vault(ctx, lamports, wsol_amount)
+    }
+
+    pub fn cancel_nonces(ctx: Context<CancelNonces>, word_pos: u64, mask: [u8; 32]) -> Result<()> {
+        handle_cancel_nonces(ctx, word_pos, mask)
+    }
+
+    pub fn cancel_taker_nonces(ctx: Context<CancelTakerNonces>, word_pos: u64, mask: [u8; 32]) -> Result<()> {
+        handle_cancel_taker_nonces(ctx, word_pos, mask)
+    }
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey, fee_recipient: Pubkey) -> Result<()> {
+        handle_initialize_config(ctx, admin, fee_recipient)
+    }
+
+    pub fn set_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        handle_set_admin(ctx, new_admin)
+    }
+
+    pub fn set_fee_recipient(ctx: Context<UpdateConfig>, fee_recipient: Pubkey) -> Result<()> {
+        handle_set_fee_recipient(ctx, fee_recipient)
+    }
+
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        handle_set_paused(ctx, paused)
+    }
+
+    pub fn record_output_balance(ctx: Context<RecordOutputBalance>) -> Result<()> {
+        handle_record_output_balance(ctx)
+    }
+
+    pub fn assert_min_output(ctx: Context<AssertMinOutput>, min_output_amount: u64) -> Result<()> {
+        handle_assert_min_output(ctx, min_output_amount)
     }
 }
@@ -0,0 +1,14 @@
+use anchor_lang::prelude::*;
+
+pub const CONFIG: &[u8] = b"config";
+
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    /// Owner of every `fee_recipient_token_account`
+    pub fee_recipient: Pubkey,
+    pub paused: bool,
+    pub bump: u8,
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::{bebop_rfq::DelegateLimits, error::BebopError};
+
+pub const MAKER_DELEGATE: &[u8] = b"maker-delegate";
+pub const MAX_DELEGATE_MINTS: usize = 8;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+
+/// Hot key allowed to sign `swap` on behalf of the maker within `limits`
+#[account]
+#[derive(InitSpace)]
+pub struct MakerDelegate {
+    pub maker: Pubkey,
+    pub delegate: Pubkey,
+    pub limits: DelegateLimits,
+    /// Unix day (UTC) `day_volumes` belong to
+    pub day: i64,
+    /// Maker output filled by the delegate during `day`, per `limits.mints` entry
+    #[max_len(MAX_DELEGATE_MINTS)]
+    pub day_volumes: Vec<u64>,
+    pub bump: u8,
+}
+
+impl MakerDelegate {
+    /// Checks expiry and mints of a fill signed by the delegate
+    pub fn authorize(&self, input_mint: &Pubkey, output_mint: &Pubkey, slot: u64) -> Result<()> {
+        require!(slot <= self.limits.expiry_slot, BebopError::DelegateExpired);
+        require!(
+            self.limits.mints.is_empty() || (self.mint_index(input_mint).is_some() && self.mint_index(output_mint).is_some()),
+            BebopError::DelegateMintNotAllowed
+        );
+        Ok(())
+    }
+
+    /// Adds maker output of a fill to the daily volume of `output_mint`, enforcing both limits of the mint
+    pub fn record_fill(&mut self, output_mint: &Pubkey, amount: u64, unix_timestamp: i64) -> Result<()> {
+        let day = unix_timestamp.div_euclid(SECONDS_PER_DAY);
+        if day != self.day {
+            self.day = day;
+            self.day_volumes.iter_mut().for_each(|day_volume| *day_volume = 0);
+        }
+        let Some(index) = self.mint_index(output_mint) else {
+            return Ok(());
+        };
+        let limit = self.limits.mints[index];
+        if let Some(max_fill_amount) = limit.max_fill_amount {
+            require!(amount <= max_fill_amount, BebopError::DelegateFillLimitExceeded);
+        }
+        let day_volume = &mut self.day_volumes[index];
+        *day_volume = day_volume.saturating_add(amount);
+        if let Some(max_daily_volume) = limit.max_daily_volume {
+            require!(*day_volume <= max_daily_volume, BebopError::DelegateDailyLimitExceeded);
+        }
+        Ok(())
+    }
+
+    /// Today's volume of `mint`, zero when the mint isn't listed
+    pub fn day_volume(&self, mint: &Pubkey) -> u64 {
+        self.mint_index(mint).map_or(0, |index| self.day_volumes[index])
+    }
+
+    fn mint_index(&self, mint: &Pubkey) -> Option<usize> {
+        self.limits.mints.iter().position(|limit| limit.mint == *mint)
+    }
+}
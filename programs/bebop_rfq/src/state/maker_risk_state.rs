@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::error::BebopError;
+
+pub const MAKER_RISK_STATE: &[u8] = b"maker-risk-state";
+
+
+/// Maker's cap on output filled for one mint pair within a rolling window of slots.
+/// The rolling volume is estimated from the current and previous fixed windows,
+/// the previous one weighted by its overlap with the rolling window
+#[account]
+#[derive(InitSpace)]
+pub struct MakerRiskState {
+    pub maker: Pubkey,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub window_slots: u64,
+    /// Ceiling of maker output within `window_slots`, in output mint units
+    pub max_volume: u64,
+    pub window_start_slot: u64,
+    pub current_volume: u64,
+    pub previous_volume: u64,
+    pub bump: u8,
+}
+
+impl MakerRiskState {
+    pub fn rolling_volume(&mut self, slot: u64) -> u64 {
+        let elapsed = slot.saturating_sub(self.window_start_slot);
+        if elapsed >= 2 * self.window_slots {
+            self.previous_volume = 0;
+            self.current_volume = 0;
+            self.window_start_slot = slot;
+        } else if elapsed >= self.window_slots {
+            self.previous_volume = self.current_volume;
+            self.current_volume = 0;
+            self.window_start_slot += self.window_slots;
+        }
+        let elapsed = slot.saturating_sub(self.window_start_slot);
+        let previous_volume = self.previous_volume as u128 * (self.window_slots - elapsed) as u128 / self.window_slots as u128;
+        (previous_volume as u64).saturating_add(self.current_volume)
+    }
+
+    /// Adds maker output of a fill, fails when the rolling volume goes above the cap
+    pub fn record_fill(&mut self, amount: u64, slot: u64) -> Result<()> {
+        let rolling_volume = self.rolling_volume(slot).saturating_add(amount);
+        require!(rolling_volume <= self.max_volume, BebopError::MakerVolumeCapExceeded);
+        self.current_volume = self.current_volume.saturating_add(amount);
+        Ok(())
+    }
+}
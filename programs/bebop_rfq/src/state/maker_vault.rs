@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+pub const MAKER_VAULT: &[u8] = b"maker-vault";
+
+
+/// Maker's inventory of one mint, settled by `swap_from_vault` without the maker signature
+#[account]
+#[derive(InitSpace)]
+pub struct MakerVault {
+    pub maker: Pubkey,
+    pub mint: Pubkey,
+    /// Hot key allowed to fill quotes from the vault, set by the maker
+    pub operator: Pubkey,
+    /// Token account owned by the vault, seeds: `MAKER_VAULT`, vault address
+    pub token_account: Pubkey,
+    pub bump: u8,
+}
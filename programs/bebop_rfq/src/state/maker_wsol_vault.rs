@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+pub const MAKER_WSOL_VAULT: &[u8] = b"maker-wsol-vault";
+
+
+/// Maker's persistent unwrap vault: lamports above rent pay native SOL legs,
+/// the matching WSOL is moved into `token_account` instead of a temporary account
+#[account]
+#[derive(InitSpace)]
+pub struct MakerWsolVault {
+    pub maker: Pubkey,
+    /// WSOL token account owned by the vault, seeds: `MAKER_WSOL_VAULT`, vault address
+    pub token_account: Pubkey,
+    pub bump: u8,
+}
@@ -0,0 +1,15 @@
+mod config;
+mod maker_delegate;
+mod maker_risk_state;
+mod maker_vault;
+mod maker_wsol_vault;
+mod nonce_bitmap;
+mod output_snapshot;
+
+pub use config::*;
+pub use maker_delegate::*;
+pub use maker_risk_state::*;
+pub use maker_vault::*;
+pub use maker_wsol_vault::*;
+pub use nonce_bitmap::*;
+pub use output_snapshot::*;
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::error::BebopError;
+
+pub const NONCE_BITMAP: &[u8] = b"nonce-bitmap";
+pub const TAKER_NONCE_BITMAP: &[u8] = b"taker-nonce-bitmap";
+
+
+/// Unordered nonces of a maker, or of a taker's `TakerOrder`s under `TAKER_NONCE_BITMAP` seeds, 256 nonces per account.
+/// Nonce `n` is bit `n & 0xff` of the bitmap with `word_pos = n >> 8`.
+#[account]
+#[derive(InitSpace)]
+pub struct NonceBitmap {
+    pub owner: Pubkey,
+    pub word_pos: u64,
+    pub bitmap: [u8; 32],
+    pub bump: u8,
+}
+
+impl NonceBitmap {
+    pub fn word_pos(nonce: u64) -> u64 {
+        nonce >> 8
+    }
+
+    pub fn use_nonce(&mut self, nonce: u64) -> Result<()> {
+        let bit_pos = (nonce & 0xff) as usize;
+        let mask = 1u8 << (bit_pos % 8);
+        require!(self.bitmap[bit_pos / 8] & mask == 0, BebopError::NonceAlreadyUsed);
+        self.bitmap[bit_pos / 8] |= mask;
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, mask: [u8; 32]) {
+        for (word, mask) in self.bitmap.iter_mut().zip(mask) {
+            *word |= mask;
+        }
+    }
+}
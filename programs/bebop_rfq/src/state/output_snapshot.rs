@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+pub const OUTPUT_SNAPSHOT: &[u8] = b"output-snapshot";
+
+
+/// Receiver output balance recorded by `record_output_balance`, checked and closed by `assert_min_output`
+#[account]
+#[derive(InitSpace)]
+pub struct OutputSnapshot {
+    pub taker: Pubkey,
+    pub receiver: Pubkey,
+    pub output_mint: Pubkey,
+    /// None means native SOL, balance is receiver lamports
+    pub receiver_output_mint_token_account: Option<Pubkey>,
+    pub balance: u64,
+    pub bump: u8,
+}
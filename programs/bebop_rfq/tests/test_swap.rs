@@ -1,12 +1,17 @@
 mod test_utils;
 
-use anchor_spl::associated_token::spl_associated_token_account::instruction;
-use solana_program_test::{tokio, BanksClientError};
+use anchor_lang::{event::EVENT_IX_TAG_LE, prelude::{AccountMeta, AnchorDeserialize, AnchorSerialize, Pubkey}, solana_program::instruction::{CompiledInstruction, Instruction}, AccountDeserialize, Discriminator, InstructionData};
+use anchor_spl::{associated_token::{get_associated_token_address_with_program_id, spl_associated_token_account::instruction}, token::spl_token};
+use bebop_rfq_client::{bebop_lookup_table_addresses, create_shared_token_account_instruction, decode_logged_swap_fills, decode_swap_fills, deposit_instruction, event_authority_address, maker_risk_state_address, maker_vault_token_account_address, maker_wsol_vault_address, maker_wsol_vault_token_account_address, revoke_delegate_instruction, set_vault_operator_instruction, shared_account_address, sweep_shared_instruction, taker_nonce_bitmap_address, withdraw_instruction, withdraw_maker_wsol_vault_instruction, AccountKind as ClientAccountKind, ClientError, FillTaker, Hop, InnerInstructions, PoolAccounts, PoolHop, RfqHop, Route, SwapBuilder, SwapFill, SwapFillDetails, Taker};
+use solana_program_test::tokio;
 use assert_matches::assert_matches;
-use solana_sdk::{signer::Signer, transaction::TransactionError};
+use solana_sdk::{account::AccountSharedData, message::Message, packet::PACKET_DATA_SIZE, signer::{keypair::Keypair, Signer}, system_instruction, transaction::{Transaction, TransactionError}};
 use test_case::test_case;
-use test_utils::{create_onchain_swap_instruction, get_associated_token_account, mint_balance, prepare_test, sign_and_execute_tx, AccountKind, Accounts, BalanceChecker, BalanceReader, MiddleTokenInfo, OnchainSwapType, OnchainTokens, ReceiverKind, TestEnvironment, TestMode};
+use test_utils::{assert_expected_error, create_assert_min_output_instruction, create_cancel_nonces_instruction, create_cancel_taker_nonces_instruction, create_initialize_config_instruction, create_record_output_balance_instruction, create_onchain_swap_instruction, create_set_admin_instruction, create_set_fee_recipient_instruction, create_set_paused_instruction, create_test_lookup_table, get_associated_token_account, process_and_assert_ok, process_instructions, mint_balance, prepare_test, sign_and_execute_tx, sign_and_execute_v0_tx, simulate_compute_units, simulate_swap_fills, AccountKind, Accounts, BalanceChecker, BalanceReader, MiddleTokenInfo, OnchainSwapType, OnchainTokens, ReceiverKind, TestEnvironment, TestMode};
 use spl_token_client::token::ExtensionInitializationParams;
+use bebop_rfq::{bebop_rfq::{AmountWithExpiry, DelegateLimits, DelegateMintLimit, PositiveSlippage, RelayerFee, SharedAccountSeeds, TransferFeeMode}, events::{BebopSwap, TransferKind, EVENT_VERSION}};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use anchor_spl::token_2022::spl_token_2022::state::AccountState;
 
 
 
@@ -16,17 +21,40 @@ use spl_token_client::token::ExtensionInitializationParams;
 #[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, ..Default::default()})]
 #[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, ..Default::default()})]
 #[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_wsol_vault: true, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_wsol_vault: true, ..Default::default()})]
 #[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, ..Default::default()})]
 #[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, ..Default::default()})]
 #[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, token_2022_native_mint: true, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, token_2022_native_mint: true, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, token_2022_native_mint: true, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, token_2022_native_mint: true, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, token_2022_native_mint: true, maker_wsol_vault: true, expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidMakerWsolVault)))), ..Default::default()})]
 #[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 0, maximum_fee: 0 }]), ..Default::default()})]
 #[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, output_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 0, maximum_fee: 0 }]), ..Default::default()})]
-#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 100, maximum_fee: u64::MAX }]), expected_error: Some(solana_sdk::transaction::TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::Token2022MintExtensionNotSupported)))), ..Default::default()})]
-#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::NonTransferable]), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(anchor_spl::token_2022::spl_token_2022::error::TokenError::NonTransferable as u32))), ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 100, maximum_fee: u64::MAX }]), ..Default::default()})]
+#[test_case(TestMode { input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 100, maximum_fee: u64::MAX }]), transfer_fee_mode: TransferFeeMode::Net, ..Default::default()})]
+#[test_case(TestMode { output_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 50, maximum_fee: 5_000_000 }]), fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], output_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 50, maximum_fee: u64::MAX }]), fee_bps: 30, transfer_fee_mode: TransferFeeMode::Net, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::NonTransferable]), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::NonTransferableNotAllowed)))), ..Default::default()})]
 #[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000, 900_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000, 1_000_000_000],  receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: false, taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, ..Default::default()})]
 #[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000],  receiver_kind: ReceiverKind::Taker, use_shared_taker: false, taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, ..Default::default()})]
 #[test_case(TestMode { input_amounts: vec![1_000_000_000], output_amounts: vec![2_000_000_000], receiver_kind: ReceiverKind::TakerWithTokenAccount, use_shared_taker: false, taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, ..Default::default()})]
 #[test_case(TestMode { input_amounts: vec![1_000_000_000], output_amounts: vec![2_000_000_000], receiver_kind: ReceiverKind::Taker, use_shared_taker: false, taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, ..Default::default()})]
+#[test_case(TestMode { fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], receiver_kind: ReceiverKind::AnotherAddress, fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { fee_bps: 10_000, expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidFeeBps)))), ..Default::default()})]
+#[test_case(TestMode { input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferHook { authority: None, program_id: Some(mock_swap::ID) }]), ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], output_mint_extensions: Some(vec![ExtensionInitializationParams::TransferHook { authority: None, program_id: Some(mock_swap::ID) }]), fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { input_mint_extensions: Some(vec![ExtensionInitializationParams::PermanentDelegate { delegate: solana_sdk::pubkey::Pubkey::new_unique() }]), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::PermanentDelegateNotAllowed)))), ..Default::default()})]
+#[test_case(TestMode { output_mint_extensions: Some(vec![ExtensionInitializationParams::PermanentDelegate { delegate: solana_sdk::pubkey::Pubkey::new_unique() }]), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::PermanentDelegateNotAllowed)))), ..Default::default()})]
+#[test_case(TestMode { output_mint_extensions: Some(vec![ExtensionInitializationParams::ConfidentialTransferMint { authority: None, auto_approve_new_accounts: false, auditor_elgamal_pubkey: None }]), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::ConfidentialTransferNotAllowed)))), ..Default::default()})]
+#[test_case(TestMode { input_mint_extensions: Some(vec![ExtensionInitializationParams::DefaultAccountState { state: AccountState::Initialized }]), ..Default::default()})]
 #[tokio::test]
 async fn test_direct_swap(test_mode: TestMode) {
     assert!(!test_mode.use_shared_taker);
@@ -42,17 +70,8 @@ async fn test_direct_swap(test_mode: TestMode) {
         &env.banks_client,
     )
     .await;
-    match test_mode.expected_error {
-        Some(expected_error) => {
-            let BanksClientError::TransactionError(transaction_error) = result.unwrap_err() else {
-                panic!("The error was not a transaction error");
-            };
-            assert_eq!(transaction_error, expected_error);
-            return;
-        }
-        None => {
-            assert_matches!(result, Ok(()));
-        }
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
     }
     balance_checker.verify_balances_direct_swap(&env, test_mode).await;
 }
@@ -70,9 +89,10 @@ async fn test_direct_swap(test_mode: TestMode) {
 #[test_case(TestMode { middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 0, maximum_fee: 0 }])}), ..Default::default()})]
 #[test_case(TestMode { middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: None}), taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 0, maximum_fee: 0 }]), ..Default::default()})]
 #[test_case(TestMode { middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: None}), taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, output_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 0, maximum_fee: 0 }]), ..Default::default()})]
-#[test_case(TestMode { middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: None}), taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 100, maximum_fee: u64::MAX }]), expected_error: Some(solana_sdk::transaction::TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::Token2022MintExtensionNotSupported)))), ..Default::default()})]
-#[test_case(TestMode { middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: None}), taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::NonTransferable]), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(anchor_spl::token_2022::spl_token_2022::error::TokenError::NonTransferable as u32))), ..Default::default()})]
+#[test_case(TestMode { middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: None}), taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 100, maximum_fee: u64::MAX }]), ..Default::default()})]
+#[test_case(TestMode { middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: None}), taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::NonTransferable]), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::NonTransferableNotAllowed)))), ..Default::default()})]
 #[test_case(TestMode {middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: None}), ..Default::default()})]
+#[test_case(TestMode { middle_token_info: Some(MiddleTokenInfo{token_amount: 7_000_000_000, mint_extensions: Some(vec![ExtensionInitializationParams::TransferHook { authority: None, program_id: Some(mock_swap::ID) }])}), ..Default::default()})]
 #[tokio::test]
 async fn test_2_hops_with_makers(test_mode: TestMode) {
     // token_a -> token_c -> token_b
@@ -91,17 +111,8 @@ async fn test_2_hops_with_makers(test_mode: TestMode) {
         &env.banks_client,
     )
     .await;
-    match test_mode.expected_error {
-        Some(expected_error) => {
-            let BanksClientError::TransactionError(transaction_error) = result.unwrap_err() else {
-                panic!("The error was not a transaction error");
-            };
-            assert_eq!(transaction_error, expected_error);
-            return;
-        }
-        None => {
-            assert_matches!(result, Ok(()));
-        }
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
     }
     balance_checker.verify_balances_for_2_hops(&env, test_mode).await;
     
@@ -118,6 +129,14 @@ async fn test_2_hops_with_makers(test_mode: TestMode) {
 #[test_case(TestMode { receiver_kind: ReceiverKind::Taker, use_shared_taker: true, onchain_swap_type: Some(OnchainSwapType::RaydiumCPMM), input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 0, maximum_fee: 0 }]), ..Default::default()})]
 #[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::RaydiumCLMM), receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: true, ..Default::default()})]
 #[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::MeteoraDLMM), receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: true, ..Default::default()})]
+#[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::MeteoraDLMM), receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: true, surplus_to_receiver: true, ..Default::default()})]
+#[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::MeteoraDLMM), receiver_kind: ReceiverKind::Taker, use_shared_taker: true, surplus_to_receiver: true, ..Default::default()})]
+#[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::RaydiumCLMM), receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: true, surplus_to_receiver: true, ..Default::default()})]
+#[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::MeteoraDLMM), receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: true, positive_slippage: Some(PositiveSlippage { taker_share_bps: 5_000, max_output_amount: None }), ..Default::default()})]
+#[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::MeteoraDLMM), receiver_kind: ReceiverKind::Taker, use_shared_taker: true, positive_slippage: Some(PositiveSlippage { taker_share_bps: 10_000, max_output_amount: None }), ..Default::default()})]
+#[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::MeteoraDLMM), receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: true, positive_slippage: Some(PositiveSlippage { taker_share_bps: 5_000, max_output_amount: Some(2_200_000_000) }), ..Default::default()})]
+#[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::RaydiumCLMM), receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: true, positive_slippage: Some(PositiveSlippage { taker_share_bps: 5_000, max_output_amount: None }), ..Default::default()})]
+#[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::MeteoraDLMM), receiver_kind: ReceiverKind::AnotherAddress, use_shared_taker: true, positive_slippage: Some(PositiveSlippage { taker_share_bps: 10_001, max_output_amount: None }), expected_error: Some(TransactionError::InstructionError(2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidSlippageShareBps)))), ..Default::default()})]
 #[tokio::test]
 async fn test_swap_from_pda(test_mode: TestMode) {
     // taker --onchain-swap--> shared_pda <--SWAP--> makers
@@ -138,8 +157,14 @@ async fn test_swap_from_pda(test_mode: TestMode) {
             (test_mode.input_amounts.iter().sum::<u64>() / 2, test_mode.output_amounts.iter().sum::<u64>() / 2)
         }
         Some(OnchainSwapType::MeteoraDLMM) => {
-            // in case of MeteoraDLMM apply positive slippage for the output, all positive slippage goes to maker
-            (3 * test_mode.input_amounts.iter().sum::<u64>() / 2, test_mode.output_amounts.iter().sum::<u64>())
+            // in case of MeteoraDLMM apply positive slippage 50% for the output, it goes to maker unless surplus_to_receiver
+            // or shared with the receiver by positive_slippage policy at the quote rate
+            let output_amount = test_mode.output_amounts.iter().sum::<u64>();
+            let slippage_output_amount = test_mode.positive_slippage.map_or(0, |policy| {
+                let slippage_output_amount = output_amount / 2 * policy.taker_share_bps as u64 / 10_000;
+                policy.max_output_amount.map_or(slippage_output_amount, |max| slippage_output_amount.min(max - output_amount))
+            });
+            (3 * test_mode.input_amounts.iter().sum::<u64>() / 2, output_amount + slippage_output_amount)
         }
         _ =>  (test_mode.input_amounts.iter().sum(), test_mode.output_amounts.iter().sum())
     };
@@ -162,17 +187,8 @@ async fn test_swap_from_pda(test_mode: TestMode) {
         &env.banks_client,
     )
     .await;
-    match test_mode.expected_error {
-        Some(expected_error) => {
-            let BanksClientError::TransactionError(transaction_error) = result.unwrap_err() else {
-                panic!("The error was not a transaction error");
-            };
-            assert_eq!(transaction_error, expected_error);
-            return;
-        }
-        None => {
-            assert_matches!(result, Ok(()));
-        }
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
     }
     balance_checker.verify_balances_swap_from_pda(
         &env, test_mode, taker_token_c_input, onchain_swap_output, final_swap_output
@@ -181,6 +197,63 @@ async fn test_swap_from_pda(test_mode: TestMode) {
 }
 
 
+#[tokio::test]
+async fn test_swap_from_other_route_shared_account() {
+    // seeds of route 1 can't spend the balance of route 0 shared account
+    let test_mode = TestMode { use_shared_taker: true, ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), false).await;
+    mint_balance(test_mode.input_amounts[0], env.shared_token_a_account, &env.token_a, AccountKind::Token, &env.banks_client, &env.payer).await;
+    let mut swap_instruction = all_instructions[1].clone();
+    let mut data = bebop_rfq::instruction::Swap::try_from_slice(&swap_instruction.data[8..]).unwrap();
+    data.shared_account = Some(SharedAccountSeeds { owner: env.taker, route_id: 1 });
+    data.bumps.shared_account = shared_account_address(&env.taker, 1).1;
+    swap_instruction.data = data.data();
+    let result = sign_and_execute_tx(
+        &[all_instructions[0].clone(), swap_instruction],
+        &env.payer,
+        &env.taker_keypair,
+        &env.makers_keypairs[..1],
+        &env.banks_client,
+    )
+    .await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::WrongSharedAccountAddress))
+    )));
+}
+
+
+#[tokio::test]
+async fn test_swap_from_other_taker_shared_account() {
+    // third party can't spend taker's shared account, neither signing as the owner nor omitting it
+    let test_mode = TestMode { use_shared_taker: true, ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), false).await;
+    mint_balance(test_mode.input_amounts[0], env.shared_token_a_account, &env.token_a, AccountKind::Token, &env.banks_client, &env.payer).await;
+    let attacker = Keypair::new();
+    for shared_account_owner in [AccountMeta::new_readonly(attacker.pubkey(), true), AccountMeta::new_readonly(bebop_rfq::ID, false)] {
+        let mut swap_instruction = all_instructions[1].clone();
+        swap_instruction
+            .accounts
+            .iter_mut()
+            .filter(|account| account.pubkey == env.taker && account.is_signer)
+            .for_each(|account| *account = shared_account_owner.clone());
+        let result = sign_and_execute_tx(
+            &[all_instructions[0].clone(), swap_instruction],
+            &env.payer,
+            &attacker,
+            &env.makers_keypairs[..1],
+            &env.banks_client,
+        )
+        .await;
+        assert_expected_error(result, Some(&TransactionError::InstructionError(
+            1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::SharedAccountOwnerNotSigner))
+        )));
+    }
+    assert_eq!(env.token_a.get_account_info(&env.shared_token_a_account.unwrap()).await.unwrap().base.amount, test_mode.input_amounts[0]);
+}
+
+
 #[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::RaydiumCPMM), taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token },  ..Default::default()})]
 #[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::RaydiumCPMM), taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, ..Default::default()})]
 #[test_case(TestMode { onchain_swap_type: Some(OnchainSwapType::RaydiumCPMM), taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, ..Default::default()})]
@@ -216,17 +289,1174 @@ async fn test_swap_then_onchain_swap(test_mode: TestMode) {
         &env.banks_client,
     )
     .await;
-    match test_mode.expected_error {
-        Some(expected_error) => {
-            let BanksClientError::TransactionError(transaction_error) = result.unwrap_err() else {
-                panic!("The error was not a transaction error");
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
+    }
+    balance_checker.verify_balances_for_swap_then_onchain(&env, test_mode, onchain_pool_output_token_c).await;
+}
+
+
+#[tokio::test]
+async fn test_swap_when_paused() {
+    let test_mode = TestMode::default();
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+
+    // only admin can pause
+    let result = process_instructions(
+        &[create_set_paused_instruction(env.config, env.taker, true)],
+        &env.payer,
+        &[&env.taker_keypair],
+        &env.banks_client,
+    ).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        0, solana_sdk::instruction::InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintHasOne.into())
+    )));
+
+    let result = process_instructions(
+        &[create_set_paused_instruction(env.config, env.payer.pubkey(), true)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+    assert_matches!(result, Ok(()));
+    assert!(env.get_config().await.paused);
+
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let cur_makers = &env.makers_keypairs[..test_mode.input_amounts.len()];
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        cur_makers,
+        &env.banks_client,
+    )
+    .await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::ProgramPaused))
+    )));
+
+    let result = process_instructions(
+        &[create_set_paused_instruction(env.config, env.payer.pubkey(), false)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+    assert_matches!(result, Ok(()));
+    assert!(!env.get_config().await.paused);
+}
+
+
+#[tokio::test]
+async fn test_initialize_config() {
+    let env: TestEnvironment = prepare_test(TestMode::default()).await;
+    // start over from a program without config
+    env.context.lock().await.set_account(&env.config, &AccountSharedData::default());
+
+    // anyone but the upgrade authority could take over the admin
+    let result = process_instructions(
+        &[create_initialize_config_instruction(env.taker, env.taker, env.taker)],
+        &env.payer,
+        &[&env.taker_keypair],
+        &env.banks_client,
+    ).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        0, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::NotUpgradeAuthority))
+    )));
+
+    process_and_assert_ok(
+        &[create_initialize_config_instruction(env.payer.pubkey(), env.payer.pubkey(), env.fee_recipient)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+    let config = env.get_config().await;
+    assert_eq!((config.admin, config.fee_recipient, config.paused), (env.payer.pubkey(), env.fee_recipient, false));
+}
+
+
+#[tokio::test]
+async fn test_set_admin() {
+    let env: TestEnvironment = prepare_test(TestMode::default()).await;
+
+    let result = process_instructions(
+        &[create_set_admin_instruction(env.config, env.payer.pubkey(), env.taker)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+    assert_matches!(result, Ok(()));
+    assert_eq!(env.get_config().await.admin, env.taker);
+
+    // previous admin can't pause anymore
+    let result = process_instructions(
+        &[create_set_paused_instruction(env.config, env.payer.pubkey(), true)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        0, solana_sdk::instruction::InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintHasOne.into())
+    )));
+
+    let result = process_instructions(
+        &[create_set_paused_instruction(env.config, env.taker, true)],
+        &env.payer,
+        &[&env.taker_keypair],
+        &env.banks_client,
+    ).await;
+    assert_matches!(result, Ok(()));
+    assert!(env.get_config().await.paused);
+}
+
+
+
+#[tokio::test]
+async fn test_fee_recipient() {
+    let test_mode = TestMode { fee_bps: 30, ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    assert_eq!(env.get_config().await.fee_recipient, env.fee_recipient);
+    let cur_makers = &env.makers_keypairs[..1];
+    let taker_token_b_account = env.taker_token_b_account.unwrap();
+    let redirect_fee = |mut instructions: Vec<Instruction>| {
+        instructions[1].accounts.iter_mut()
+            .filter(|account| account.pubkey == env.fee_recipient_token_b_account)
+            .for_each(|account| account.pubkey = taker_token_b_account);
+        instructions
+    };
+
+    // fee sent to a token account not owned by config fee_recipient
+    let all_instructions = redirect_fee(env.create_single_swap_instructions(test_mode.clone(), true).await);
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        1, solana_sdk::instruction::InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintTokenOwner.into())
+    )));
+
+    // only admin moves the fee recipient
+    let result = process_instructions(
+        &[create_set_fee_recipient_instruction(env.config, env.taker, env.taker)],
+        &env.payer,
+        &[&env.taker_keypair],
+        &env.banks_client,
+    ).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        0, solana_sdk::instruction::InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintHasOne.into())
+    )));
+    process_and_assert_ok(
+        &[create_set_fee_recipient_instruction(env.config, env.payer.pubkey(), env.taker)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+    assert_eq!(env.get_config().await.fee_recipient, env.taker);
+
+    let all_instructions = redirect_fee(env.create_single_swap_instructions(TestMode { nonce: 1, ..test_mode.clone() }, true).await);
+    let taker_output = BalanceReader::new(&env.token_b, env.taker, &env.taker_token_b_account);
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_matches!(result, Ok(()));
+    // receiver and new fee recipient are the same token account
+    assert_eq!(taker_output.get_balance().await, test_mode.output_amounts[0]);
+}
+
+
+#[tokio::test]
+async fn test_sweep_shared() {
+    let env: TestEnvironment = prepare_test(TestMode::default()).await;
+    // dust left in shared-pda account by a previous route
+    let (dust_amount, dust_lamports) = (1_234, 5_000_000);
+    mint_balance(dust_amount, env.shared_token_a_account, &env.token_a, AccountKind::Token, &env.banks_client, &env.payer).await;
+    process_and_assert_ok(
+        &[system_instruction::transfer(&env.payer.pubkey(), &env.shared_pda, dust_lamports)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+    let rent_exempt_lamports = env.banks_client.lock().await.get_rent().await.unwrap().minimum_balance(0);
+    let taker_token_a = BalanceReader::new(&env.token_a, env.taker, &env.taker_token_a_account);
+    let taker_native = BalanceReader::new(&env.token_a, env.taker, &None);
+    let shared_token_a = BalanceReader::new(&env.token_a, env.shared_pda, &env.shared_token_a_account);
+    let shared_native = BalanceReader::new(&env.token_a, env.shared_pda, &None);
+    let (taker_token_a_before, taker_native_before) = (taker_token_a.get_balance().await, taker_native.get_balance().await);
+
+    let sweep_instruction = sweep_shared_instruction(env.taker, 0, Some((env.token_a_mint, env.token_a_program_id)), false);
+    process_and_assert_ok(&[sweep_instruction], &env.payer, &[&env.taker_keypair], &env.banks_client).await;
+    assert_eq!(taker_token_a.get_balance().await - taker_token_a_before, dust_amount);
+    assert_eq!(taker_native.get_balance().await - taker_native_before, dust_lamports - rent_exempt_lamports);
+    assert_eq!(shared_token_a.get_balance().await, 0);
+    assert_eq!(shared_native.get_balance().await, rent_exempt_lamports);
+
+    // nothing left, lamports-only sweep is a no-op
+    process_and_assert_ok(&[sweep_shared_instruction(env.taker, 0, None, false)], &env.payer, &[&env.taker_keypair], &env.banks_client).await;
+    assert_eq!(shared_native.get_balance().await, rent_exempt_lamports);
+
+    // closing returns token account rent to the taker
+    let taker_native_before = taker_native.get_balance().await;
+    let sweep_instruction = sweep_shared_instruction(env.taker, 0, Some((env.token_a_mint, env.token_a_program_id)), true);
+    process_and_assert_ok(&[sweep_instruction], &env.payer, &[&env.taker_keypair], &env.banks_client).await;
+    assert!(env.banks_client.lock().await.get_account(env.shared_token_a_account.unwrap()).await.unwrap().is_none());
+    assert!(taker_native.get_balance().await > taker_native_before);
+    process_and_assert_ok(
+        &[create_shared_token_account_instruction(&env.payer.pubkey(), &env.taker, 0, &env.token_a_mint, &env.token_a_program_id)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+
+    // token accounts must be passed together with mint and token program
+    let mut sweep_instruction = sweep_shared_instruction(env.taker, 0, Some((env.token_a_mint, env.token_a_program_id)), false);
+    sweep_instruction.accounts[5].pubkey = bebop_rfq::ID;
+    let result = process_instructions(&[sweep_instruction], &env.payer, &[&env.taker_keypair], &env.banks_client).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        0, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidSweepAccounts))
+    )));
+}
+
+
+#[tokio::test]
+async fn test_maker_wsol_vault() {
+    // maker WSOL -> receiver native SOL through the vault, then maker withdraws both sides
+    let test_mode = TestMode {
+        taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol },
+        maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint },
+        maker_wsol_vault: true,
+        ..Default::default()
+    };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let maker = env.makers[0];
+    let vault_token_account = Some(maker_wsol_vault_token_account_address(&maker));
+    let vault_native_account = Some(maker_wsol_vault_address(&maker));
+    let maker_wsol_account = env.makers_token_b_account.first().copied();
+    let maker_wsol = BalanceReader::new(&env.token_b, maker, &maker_wsol_account);
+    let vault_wsol = BalanceReader::new(&env.token_b, maker, &vault_token_account);
+    let vault_native = BalanceReader::new(&env.token_b, vault_native_account.unwrap(), &None);
+    let (maker_wsol_before, vault_native_before) = (maker_wsol.get_balance().await, vault_native.get_balance().await);
+
+    let cur_makers = &env.makers_keypairs[..1];
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_matches!(result, Ok(()));
+    let output_amount = test_mode.output_amounts[0];
+    assert_eq!(maker_wsol_before - maker_wsol.get_balance().await, output_amount);
+    assert_eq!(vault_wsol.get_balance().await, output_amount);
+    assert_eq!(vault_native_before - vault_native.get_balance().await, output_amount);
+    // temporary account isn't used
+    assert!(env.banks_client.lock().await.get_account(env.temporary_wsol_token_accounts[0]).await.unwrap().is_none());
+
+    // vault keeps its rent
+    let result = process_instructions(
+        &[withdraw_maker_wsol_vault_instruction(maker, vault_native.get_balance().await, 0)],
+        &env.payer,
+        &[&env.makers_keypairs[0]],
+        &env.banks_client,
+    ).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        0, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InsufficientWsolVaultLamports))
+    )));
+
+    let vault_native_before = vault_native.get_balance().await;
+    process_and_assert_ok(
+        &[withdraw_maker_wsol_vault_instruction(maker, 1_000_000_000, output_amount)],
+        &env.payer,
+        &[&env.makers_keypairs[0]],
+        &env.banks_client,
+    ).await;
+    assert_eq!(maker_wsol.get_balance().await, maker_wsol_before);
+    assert_eq!(vault_wsol.get_balance().await, 0);
+    assert_eq!(vault_native_before - vault_native.get_balance().await, 1_000_000_000);
+}
+
+
+#[tokio::test]
+async fn test_swap_from_vault() {
+    // token_a -> token_b settled from maker's vaults, signed by the operator instead of the maker
+    let test_mode = TestMode::default();
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let (input_amount, output_amount) = (test_mode.input_amounts[0], test_mode.output_amounts[0]);
+    let maker = env.makers[0];
+    let operator = Keypair::new();
+    mint_balance(input_amount, env.taker_token_a_account, &env.token_a, AccountKind::Token, &env.banks_client, &env.payer).await;
+    mint_balance(output_amount, env.makers_token_b_account.first().copied(), &env.token_b, AccountKind::Token, &env.banks_client, &env.payer).await;
+    process_and_assert_ok(
+        &[
+            deposit_instruction(maker, env.token_b_mint, env.token_b_program_id, output_amount),
+            deposit_instruction(maker, env.token_a_mint, env.token_a_program_id, 0),
+            set_vault_operator_instruction(maker, env.token_b_mint, operator.pubkey()),
+        ],
+        &env.payer,
+        &[&env.makers_keypairs[0]],
+        &env.banks_client,
+    ).await;
+
+    let receiver_token_account = Some(get_associated_token_address_with_program_id(&env.taker, &env.token_b_mint, &env.token_b_program_id));
+    let receiver_output = BalanceReader::new(&env.token_b, env.taker, &receiver_token_account);
+    let vault_input_account = Some(maker_vault_token_account_address(&maker, &env.token_a_mint));
+    let vault_output_account = Some(maker_vault_token_account_address(&maker, &env.token_b_mint));
+    let vault_input = BalanceReader::new(&env.token_a, maker, &vault_input_account);
+    let vault_output = BalanceReader::new(&env.token_b, maker, &vault_output_account);
+    let builder = SwapBuilder::new(Taker::Wallet(env.taker), maker, env.payer.pubkey())
+        .input_mint(env.token_a_mint, env.token_a_program_id, ClientAccountKind::Token, ClientAccountKind::Token)
+        .output_mint(env.token_b_mint, env.token_b_program_id, ClientAccountKind::Token, ClientAccountKind::Token)
+        .amounts(input_amount, vec![AmountWithExpiry { amount: output_amount, expiry: u64::MAX }]);
+    let create_receiver_ata = instruction::create_associated_token_account_idempotent(
+        &env.payer.pubkey(), &env.taker, &env.token_b_mint, &env.token_b_program_id
+    );
+
+    // maker's key isn't the operator anymore
+    let instructions = [create_receiver_ata.clone(), builder.build_from_vault(maker).unwrap()];
+    let result = sign_and_execute_tx(&instructions, &env.payer, &env.taker_keypair, &env.makers_keypairs[..1], &env.banks_client).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidVaultOperator))
+    )));
+
+    let instructions = [create_receiver_ata, builder.build_from_vault(operator.pubkey()).unwrap()];
+    let result = sign_and_execute_tx(&instructions, &env.payer, &env.taker_keypair, std::slice::from_ref(&operator), &env.banks_client).await;
+    assert_matches!(result, Ok(()));
+    assert_eq!(receiver_output.get_balance().await, output_amount);
+    assert_eq!(vault_input.get_balance().await, input_amount);
+    assert_eq!(vault_output.get_balance().await, 0);
+
+    // only the maker withdraws
+    let maker_input_account = env.makers_token_a_account.first().copied();
+    let maker_input = BalanceReader::new(&env.token_a, maker, &maker_input_account);
+    let maker_input_before = maker_input.get_balance().await;
+    process_and_assert_ok(
+        &[withdraw_instruction(maker, env.token_a_mint, env.token_a_program_id, input_amount)],
+        &env.payer,
+        &[&env.makers_keypairs[0]],
+        &env.banks_client,
+    ).await;
+    assert_eq!(maker_input.get_balance().await - maker_input_before, input_amount);
+    assert_eq!(vault_input.get_balance().await, 0);
+}
+
+
+#[tokio::test]
+async fn test_maker_risk_limit() {
+    // quotes of one maker landing in the same window, the second one goes above the cap until the maker raises it
+    let test_mode = TestMode { risk_limit: Some((1_000, 3_000_000_000)), ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let cur_makers = &env.makers_keypairs[..1];
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_matches!(result, Ok(()));
+
+    // receiver token account already exists, only the swap is sent
+    let all_instructions = env.create_single_swap_instructions(TestMode { nonce: 1, ..test_mode.clone() }, true).await;
+    let result = sign_and_execute_tx(&all_instructions[1..], &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        0, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::MakerVolumeCapExceeded))
+    )));
+
+    let test_mode = TestMode { nonce: 2, risk_limit: Some((1_000, 4_000_000_000)), ..test_mode };
+    let all_instructions = env.create_single_swap_instructions(test_mode, true).await;
+    let result = sign_and_execute_tx(&all_instructions[1..], &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_matches!(result, Ok(()));
+}
+
+
+#[tokio::test]
+async fn test_maker_risk_limit_entry_points() {
+    // every fill path records into the capped maker's risk state, another pair's PDA
+    // or a non-canonical PDA of the pair in its place is rejected
+    let test_mode = TestMode { risk_limit: Some((1_000, 1_000_000_000)), ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let (input_amount, output_amount) = (test_mode.input_amounts[0], test_mode.output_amounts[0]);
+    let maker = env.makers[0];
+    let maker_risk_state = maker_risk_state_address(&maker, &env.token_a_mint, &env.token_b_mint).0;
+    let other_pair_maker_risk_state = maker_risk_state_address(&maker, &env.token_b_mint, &env.token_a_mint).0;
+    let (non_canonical_maker_risk_state, non_canonical_bump) = (0..=u8::MAX)
+        .rev()
+        .filter_map(|bump| Pubkey::create_program_address(
+            &[bebop_rfq::state::MAKER_RISK_STATE, maker.as_ref(), env.token_a_mint.as_ref(), env.token_b_mint.as_ref(), &[bump]],
+            &bebop_rfq::ID,
+        ).ok().map(|address| (address, bump)))
+        .nth(1)
+        .unwrap();
+
+    let operator = Keypair::new();
+    mint_balance(output_amount, env.makers_token_b_account.first().copied(), &env.token_b, AccountKind::Token, &env.banks_client, &env.payer).await;
+    process_and_assert_ok(
+        &[
+            deposit_instruction(maker, env.token_b_mint, env.token_b_program_id, output_amount),
+            deposit_instruction(maker, env.token_a_mint, env.token_a_program_id, 0),
+            set_vault_operator_instruction(maker, env.token_b_mint, operator.pubkey()),
+        ],
+        &env.payer,
+        &[&env.makers_keypairs[0]],
+        &env.banks_client,
+    ).await;
+    let vault_swap_instructions = vec![
+        instruction::create_associated_token_account_idempotent(&env.payer.pubkey(), &env.taker, &env.token_b_mint, &env.token_b_program_id),
+        SwapBuilder::new(Taker::Wallet(env.taker), maker, env.payer.pubkey())
+            .input_mint(env.token_a_mint, env.token_a_program_id, ClientAccountKind::Token, ClientAccountKind::Token)
+            .output_mint(env.token_b_mint, env.token_b_program_id, ClientAccountKind::Token, ClientAccountKind::Token)
+            .amounts(input_amount, vec![AmountWithExpiry { amount: output_amount, expiry: u64::MAX }])
+            .build_from_vault(operator.pubkey())
+            .unwrap(),
+    ];
+
+    // (instructions, index of the fill, signers besides taker and payer)
+    let entry_points: [(Vec<Instruction>, u8, &[Keypair]); 4] = [
+        (env.create_single_swap_instructions(test_mode.clone(), true).await, 1, &env.makers_keypairs[..1]),
+        (env.create_signed_swap_instructions(test_mode.clone()).await, 2, &[]),
+        (env.create_batch_swap_instructions(test_mode.clone()).await, 1, &env.makers_keypairs[..1]),
+        (vault_swap_instructions, 1, std::slice::from_ref(&operator)),
+    ];
+    for (instructions, index, signers) in entry_points {
+        for substitute in [None, Some(other_pair_maker_risk_state), Some(non_canonical_maker_risk_state)] {
+            let mut instructions = instructions.clone();
+            if let Some(substitute) = substitute {
+                instructions[index as usize]
+                    .accounts
+                    .iter_mut()
+                    .filter(|account| account.pubkey == maker_risk_state)
+                    .for_each(|account| account.pubkey = substitute);
+            }
+            let result = sign_and_execute_tx(&instructions, &env.payer, &env.taker_keypair, signers, &env.banks_client).await;
+            let expected_error = match substitute {
+                Some(_) => bebop_rfq::error::BebopError::WrongMakerRiskStateAddress,
+                None => bebop_rfq::error::BebopError::MakerVolumeCapExceeded,
             };
-            assert_eq!(transaction_error, expected_error);
-            return;
+            assert_expected_error(result, Some(&TransactionError::InstructionError(
+                index, solana_sdk::instruction::InstructionError::Custom(u32::from(expected_error))
+            )));
+        }
+    }
+
+    // relayer of a maker signed order picks the bump as well, the uninitialized non-canonical PDA is still rejected
+    let mut instructions = env.create_signed_swap_instructions(test_mode.clone()).await;
+    let mut data = bebop_rfq::instruction::SwapSigned::try_from_slice(&instructions[2].data[8..]).unwrap();
+    data.bumps.maker_risk_state = non_canonical_bump;
+    instructions[2].data = data.data();
+    instructions[2]
+        .accounts
+        .iter_mut()
+        .filter(|account| account.pubkey == maker_risk_state)
+        .for_each(|account| account.pubkey = non_canonical_maker_risk_state);
+    let result = sign_and_execute_tx(&instructions, &env.payer, &env.taker_keypair, &[], &env.banks_client).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::WrongMakerRiskStateAddress))
+    )));
+}
+
+
+#[tokio::test]
+async fn test_swap_nonce_reuse() {
+    let test_mode = TestMode { nonce: 777, ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let mut all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    // same quote is filled twice
+    all_instructions.push(all_instructions[1].clone());
+    let cur_makers = &env.makers_keypairs[..test_mode.input_amounts.len()];
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        cur_makers,
+        &env.banks_client,
+    )
+    .await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::NonceAlreadyUsed))
+    )));
+}
+
+
+#[tokio::test]
+async fn test_swap_with_cancelled_nonce() {
+    let test_mode = TestMode { nonce: 777, ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+
+    // 777 = word 3, bit 9
+    let mut mask = [0u8; 32];
+    mask[1] = 1 << 1;
+    let result = process_instructions(
+        &[create_cancel_nonces_instruction(env.makers[0], 3, mask)],
+        &env.payer,
+        &[&env.makers_keypairs[0]],
+        &env.banks_client,
+    ).await;
+    assert_matches!(result, Ok(()));
+
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let cur_makers = &env.makers_keypairs[..test_mode.input_amounts.len()];
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        cur_makers,
+        &env.banks_client,
+    )
+    .await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::NonceAlreadyUsed))
+    )));
+}
+
+
+
+#[test_case(TestMode { delegate_limits: Some(DelegateLimits { expiry_slot: u64::MAX, ..Default::default() }), ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], fee_bps: 30, delegate_limits: Some(DelegateLimits { mints: vec![DelegateMintLimit::default(), DelegateMintLimit { max_fill_amount: Some(6_000_000_000), max_daily_volume: Some(6_000_000_000), ..Default::default() }], expiry_slot: u64::MAX }), ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, delegate_limits: Some(DelegateLimits { expiry_slot: u64::MAX, ..Default::default() }), ..Default::default()})]
+#[test_case(TestMode { delegate_limits: Some(DelegateLimits { mints: vec![DelegateMintLimit { max_fill_amount: Some(1), max_daily_volume: Some(1), ..Default::default() }, DelegateMintLimit::default()], expiry_slot: u64::MAX }), ..Default::default()})]
+#[test_case(TestMode { delegate_limits: Some(DelegateLimits { expiry_slot: 0, ..Default::default() }), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::DelegateExpired)))), ..Default::default()})]
+#[test_case(TestMode { delegate_limits: Some(DelegateLimits { mints: vec![DelegateMintLimit { mint: Pubkey::new_unique(), ..Default::default() }], expiry_slot: u64::MAX }), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::DelegateMintNotAllowed)))), ..Default::default()})]
+#[test_case(TestMode { delegate_limits: Some(DelegateLimits { mints: vec![DelegateMintLimit::default(), DelegateMintLimit { max_fill_amount: Some(1_999_999_999), ..Default::default() }], expiry_slot: u64::MAX }), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::DelegateFillLimitExceeded)))), ..Default::default()})]
+#[test_case(TestMode { delegate_limits: Some(DelegateLimits { mints: vec![DelegateMintLimit::default(), DelegateMintLimit { max_daily_volume: Some(1_000_000_000), ..Default::default() }], expiry_slot: u64::MAX }), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::DelegateDailyLimitExceeded)))), ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, delegate_limits: Some(DelegateLimits { expiry_slot: u64::MAX, ..Default::default() }), expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::DelegateNativeSolNotAllowed)))), ..Default::default()})]
+#[tokio::test]
+async fn test_delegated_swap(test_mode: TestMode) {
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let balance_checker: BalanceChecker = BalanceChecker::new(&env).await;
+    // delegate signs instead of the makers
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        std::slice::from_ref(&env.delegate_keypair),
+        &env.banks_client,
+    )
+    .await;
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
+    }
+    balance_checker.verify_balances_direct_swap(&env, test_mode).await;
+}
+
+
+#[tokio::test]
+async fn test_revoked_delegate() {
+    let test_mode = TestMode { delegate_limits: Some(DelegateLimits { expiry_slot: u64::MAX, ..Default::default() }), ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    process_and_assert_ok(
+        &[revoke_delegate_instruction(env.makers[0], env.delegate_keypair.pubkey())],
+        &env.payer,
+        &[&env.makers_keypairs[0]],
+        &env.banks_client,
+    ).await;
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        std::slice::from_ref(&env.delegate_keypair),
+        &env.banks_client,
+    )
+    .await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        1, solana_sdk::instruction::InstructionError::Custom(anchor_lang::error::ErrorCode::AccountNotInitialized as u32)
+    )));
+}
+
+
+#[test_case(TestMode { signed_taker_order: true, ..Default::default()})]
+#[test_case(TestMode { receiver_kind: ReceiverKind::AnotherAddress, fee_bps: 30, signed_taker_order: true, ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], signed_taker_order: true, ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, signed_taker_order: true, ..Default::default()})]
+#[test_case(TestMode { tampered_output_amount: Some(1_000_000_000), signed_taker_order: true, expected_error: Some(TransactionError::InstructionError(2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidOrderSignature)))), ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, signed_taker_order: true, expected_error: Some(TransactionError::InstructionError(2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidTakerOrderAccounts)))), ..Default::default()})]
+#[tokio::test]
+async fn test_swap_with_signed_taker_order(test_mode: TestMode) {
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let balance_checker: BalanceChecker = BalanceChecker::new(&env).await;
+    // taker isn't a signer of the transaction
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        &env.makers_keypairs[..test_mode.input_amounts.len()],
+        &env.banks_client,
+    )
+    .await;
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
+    }
+    balance_checker.verify_balances_direct_swap(&env, test_mode).await;
+}
+
+
+#[tokio::test]
+async fn test_signed_taker_order_nonce() {
+    // taker order nonce is spent in taker's own bitmap, the taker cancels it without the maker
+    let test_mode = TestMode { signed_taker_order: true, ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let cur_makers = &env.makers_keypairs[..1];
+    let mut mask = [0u8; 32];
+    mask[0] = 1;
+    process_and_assert_ok(
+        &[create_cancel_taker_nonces_instruction(env.taker, 0, mask)],
+        &env.payer,
+        &[&env.taker_keypair],
+        &env.banks_client,
+    ).await;
+
+    // maker nonce 0 is unused, taker nonce 0 is cancelled
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::NonceAlreadyUsed))
+    )));
+
+    let all_instructions = env.create_single_swap_instructions(TestMode { nonce: 1, ..test_mode.clone() }, true).await;
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_matches!(result, Ok(()));
+    let taker_nonce_bitmap = env.banks_client.lock().await.get_account(taker_nonce_bitmap_address(&env.taker, 1)).await.unwrap().unwrap();
+    let taker_nonce_bitmap = bebop_rfq::state::NonceBitmap::try_deserialize(&mut taker_nonce_bitmap.data.as_slice()).unwrap();
+    assert_eq!(taker_nonce_bitmap.owner, env.taker);
+    assert_eq!(taker_nonce_bitmap.bitmap[0], 0b11);
+}
+
+
+#[tokio::test]
+async fn test_signed_taker_order_fee() {
+    // relayer can't change the fee or its recipient after the taker signed
+    let test_mode = TestMode { fee_bps: 30, signed_taker_order: true, ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let cur_makers = &env.makers_keypairs[..1];
+    let expected_error = TransactionError::InstructionError(
+        2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidOrderSignature))
+    );
+
+    let mut all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let mut data = bebop_rfq::instruction::Swap::try_from_slice(&all_instructions[2].data[8..]).unwrap();
+    data.fee_bps *= 2;
+    all_instructions[2].data = data.data();
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_expected_error(result, Some(&expected_error));
+
+    // fee recipient token account passes the config owner check, but isn't the one signed by the taker
+    process_and_assert_ok(
+        &[create_set_fee_recipient_instruction(env.config, env.payer.pubkey(), env.taker)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+    let taker_token_b_account = env.taker_token_b_account.unwrap();
+    let mut all_instructions = env.create_single_swap_instructions(TestMode { nonce: 1, ..test_mode.clone() }, true).await;
+    all_instructions[2].accounts.iter_mut()
+        .filter(|account| account.pubkey == env.fee_recipient_token_b_account)
+        .for_each(|account| account.pubkey = taker_token_b_account);
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_expected_error(result, Some(&expected_error));
+}
+
+
+#[test_case(TestMode { relayer_fee: Some(RelayerFee { amount: 10_000_000, max_amount: 20_000_000 }), ..Default::default()})]
+#[test_case(TestMode { receiver_kind: ReceiverKind::AnotherAddress, fee_bps: 30, relayer_fee: Some(RelayerFee { amount: 10_000_000, max_amount: 20_000_000 }), ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], relayer_fee: Some(RelayerFee { amount: 10_000_000, max_amount: 20_000_000 }), ..Default::default()})]
+#[test_case(TestMode { signed_taker_order: true, relayer_fee: Some(RelayerFee { amount: 10_000_000, max_amount: 20_000_000 }), ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, relayer_fee: Some(RelayerFee { amount: 10_000_000, max_amount: 20_000_000 }), ..Default::default()})]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, relayer_fee: Some(RelayerFee { amount: 10_000_000, max_amount: 20_000_000 }), ..Default::default()})]
+#[test_case(TestMode { relayer_fee: Some(RelayerFee { amount: 30_000_000, max_amount: 20_000_000 }), expected_error: Some(TransactionError::InstructionError(2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::RelayerFeeExceeded)))), ..Default::default()})]
+#[test_case(TestMode { signed_taker_order: true, relayer_fee: Some(RelayerFee { amount: 30_000_000, max_amount: 20_000_000 }), expected_error: Some(TransactionError::InstructionError(3, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::RelayerFeeExceeded)))), ..Default::default()})]
+#[tokio::test]
+async fn test_swap_with_relayer_fee(test_mode: TestMode) {
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let balance_checker: BalanceChecker = BalanceChecker::new(&env).await;
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        &env.makers_keypairs[..test_mode.input_amounts.len()],
+        &env.banks_client,
+    )
+    .await;
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
+    }
+    balance_checker.verify_balances_direct_swap(&env, test_mode).await;
+}
+
+
+#[tokio::test]
+async fn test_signed_taker_order_relayer_fee_redirected() {
+    // another fee payer can't resubmit the taker order to collect the relayer fee
+    let test_mode = TestMode { signed_taker_order: true, relayer_fee: Some(RelayerFee { amount: 10_000_000, max_amount: 20_000_000 }), ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let cur_makers = &env.makers_keypairs[..1];
+    let relayer = Keypair::new();
+    let relayer_token_account = get_associated_token_address_with_program_id(&relayer.pubkey(), &env.token_b_mint, &env.token_b_program_id);
+    process_and_assert_ok(
+        &[system_instruction::transfer(&env.payer.pubkey(), &relayer.pubkey(), 1_000_000_000)],
+        &env.payer,
+        &[],
+        &env.banks_client,
+    ).await;
+
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let payer_token_account = get_associated_token_address_with_program_id(&env.payer.pubkey(), &env.token_b_mint, &env.token_b_program_id);
+    // same instructions paid by the other relayer, including its fee token account creation
+    let mut redirected_instructions = all_instructions.clone();
+    for account in redirected_instructions.iter_mut().flat_map(|instruction| instruction.accounts.iter_mut()) {
+        if account.pubkey == env.payer.pubkey() {
+            account.pubkey = relayer.pubkey();
+        } else if account.pubkey == payer_token_account {
+            account.pubkey = relayer_token_account;
         }
-        None => {
-            assert_matches!(result, Ok(()));
+    }
+    let swap_index = redirected_instructions.len() - 1;
+    let result = sign_and_execute_tx(redirected_instructions.as_slice(), &relayer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_expected_error(result, Some(&TransactionError::InstructionError(
+        swap_index as u8, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidOrderSignature))
+    )));
+
+    let balance_checker: BalanceChecker = BalanceChecker::new(&env).await;
+    let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_matches!(result, Ok(()));
+    balance_checker.verify_balances_direct_swap(&env, test_mode).await;
+}
+
+
+#[test_case(TestMode { ..Default::default()})]
+#[test_case(TestMode { receiver_kind: ReceiverKind::AnotherAddress, ..Default::default()})]
+#[test_case(TestMode { fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { output_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 0, maximum_fee: 0 }]), ..Default::default()})]
+#[test_case(TestMode { output_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 100, maximum_fee: u64::MAX }]), transfer_fee_mode: TransferFeeMode::Net, ..Default::default()})]
+#[test_case(TestMode { tampered_output_amount: Some(2_000_000_001), expected_error: Some(TransactionError::InstructionError(2, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidOrderSignature)))), ..Default::default()})]
+#[test_case(TestMode { output_mint_extensions: Some(vec![ExtensionInitializationParams::TransferHook { authority: None, program_id: Some(mock_swap::ID) }]), ..Default::default()})]
+#[tokio::test]
+async fn test_signed_swap(test_mode: TestMode) {
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_signed_swap_instructions(test_mode.clone()).await;
+    let balance_checker: BalanceChecker = BalanceChecker::new(&env).await;
+    // makers don't sign the transaction
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        &[],
+        &env.banks_client,
+    )
+    .await;
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
+    }
+    balance_checker.verify_balances_direct_swap(&env, test_mode).await;
+}
+
+
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000, 900_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000, 1_000_000_000], ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], receiver_kind: ReceiverKind::AnotherAddress, fee_bps: 30, ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, ..Default::default()})]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], fee_bps: 10_000, expected_error: Some(TransactionError::InstructionError(1, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::InvalidFeeBps)))), ..Default::default()})]
+#[tokio::test]
+async fn test_batch_swap(test_mode: TestMode) {
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_batch_swap_instructions(test_mode.clone()).await;
+    let balance_checker: BalanceChecker = BalanceChecker::new(&env).await;
+    let cur_makers = &env.makers_keypairs[..test_mode.input_amounts.len()];
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        cur_makers,
+        &env.banks_client,
+    )
+    .await;
+    if assert_expected_error(result, test_mode.expected_error.as_ref()) {
+        return;
+    }
+    balance_checker.verify_balances_direct_swap(&env, test_mode).await;
+}
+
+
+#[tokio::test]
+async fn test_batch_swap_tx_size() {
+    // makers of one pair share taker, receiver, mints and programs accounts in a single instruction
+    let test_mode = TestMode { input_amounts: vec![1_000_000_000; 3], output_amounts: vec![2_000_000_000; 3], ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let tx_size = |instructions: &[Instruction]| {
+        bincode::serialize(&Transaction::new_unsigned(Message::new(instructions, Some(&env.payer.pubkey())))).unwrap().len()
+    };
+    let swap_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    let batch_instructions = env.create_batch_swap_instructions(test_mode.clone()).await;
+    let (swap_tx_size, batch_tx_size) = (tx_size(&swap_instructions), tx_size(&batch_instructions));
+    assert!(batch_tx_size < swap_tx_size, "batch {} bytes >= separate swaps {} bytes", batch_tx_size, swap_tx_size);
+
+    let balance_checker: BalanceChecker = BalanceChecker::new(&env).await;
+    let cur_makers = &env.makers_keypairs[..test_mode.input_amounts.len()];
+    let result = sign_and_execute_tx(batch_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+    assert_matches!(result, Ok(()));
+    balance_checker.verify_balances_direct_swap(&env, test_mode).await;
+}
+
+
+#[test_case(TestMode { ..Default::default()}, 2_000_000_000)]
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], ..Default::default()}, 8_000_000_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, ..Default::default()}, 2_000_000_000)]
+#[test_case(TestMode { expected_error: Some(TransactionError::InstructionError(3, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::MinOutputNotReached)))), ..Default::default()}, 2_000_000_001)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, expected_error: Some(TransactionError::InstructionError(3, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::MinOutputNotReached)))), ..Default::default()}, 2_000_000_001)]
+#[tokio::test]
+async fn test_swap_with_min_output(test_mode: TestMode, min_output_amount: u64) {
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let mut all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    // right after receiver token account creation and after the last leg
+    all_instructions.insert(1, create_record_output_balance_instruction(env.taker, env.taker, env.token_b_mint, env.taker_token_b_account));
+    all_instructions.push(create_assert_min_output_instruction(env.taker, env.taker, env.token_b_mint, env.taker_token_b_account, min_output_amount));
+    let cur_makers = &env.makers_keypairs[..test_mode.input_amounts.len()];
+    let result = sign_and_execute_tx(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        cur_makers,
+        &env.banks_client,
+    )
+    .await;
+    assert_expected_error(result, test_mode.expected_error.as_ref());
+}
+
+#[tokio::test]
+async fn test_swap_with_min_output_native_sol_receiver_spending() {
+    // native SOL receiver's lamports spent between the snapshot and the assert count against its output
+    let test_mode = TestMode {
+        taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol },
+        maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint },
+        ..Default::default()
+    };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let cur_makers = &env.makers_keypairs[..1];
+    let spent_lamports = 1_000_000;
+    for (i, (min_output_amount, expected_error)) in [
+        (2_000_000_000 - spent_lamports + 1, Some(bebop_rfq::error::BebopError::MinOutputNotReached)),
+        (2_000_000_000 - spent_lamports, None),
+    ].into_iter().enumerate() {
+        let mut all_instructions = env.create_single_swap_instructions(TestMode { nonce: i as u64, ..test_mode.clone() }, i == 0).await;
+        all_instructions.insert(1, create_record_output_balance_instruction(env.taker, env.taker, env.token_b_mint, None));
+        all_instructions.push(system_instruction::transfer(&env.taker, &env.payer.pubkey(), spent_lamports));
+        all_instructions.push(create_assert_min_output_instruction(env.taker, env.taker, env.token_b_mint, None, min_output_amount));
+        let assert_index = all_instructions.len() as u8 - 1;
+        let result = sign_and_execute_tx(all_instructions.as_slice(), &env.payer, &env.taker_keypair, cur_makers, &env.banks_client).await;
+        let expected_error = expected_error.map(|error| TransactionError::InstructionError(
+            assert_index, solana_sdk::instruction::InstructionError::Custom(u32::from(error))
+        ));
+        assert_expected_error(result, expected_error.as_ref());
+    }
+}
+
+#[tokio::test]
+async fn test_record_output_balance_without_assert() {
+    // a snapshot is only recorded with its `assert_min_output` later in the same transaction
+    let env: TestEnvironment = prepare_test(TestMode::default()).await;
+    let record_instruction = create_record_output_balance_instruction(env.taker, env.taker, env.token_b_mint, env.taker_token_b_account);
+    for instructions in [
+        vec![record_instruction.clone()],
+        // assert of the payer's snapshot doesn't close the taker's one
+        vec![
+            record_instruction.clone(),
+            create_record_output_balance_instruction(env.payer.pubkey(), env.payer.pubkey(), env.token_b_mint, None),
+            create_assert_min_output_instruction(env.payer.pubkey(), env.payer.pubkey(), env.token_b_mint, None, 0),
+        ],
+    ] {
+        let result = process_instructions(&instructions, &env.payer, &[&env.taker_keypair], &env.banks_client).await;
+        assert_expected_error(result, Some(&TransactionError::InstructionError(
+            0, solana_sdk::instruction::InstructionError::Custom(u32::from(bebop_rfq::error::BebopError::MissingAssertMinOutput))
+        )));
+    }
+}
+
+#[tokio::test]
+async fn test_record_output_balance_token_account() {
+    let env: TestEnvironment = prepare_test(TestMode::default()).await;
+    // receiver's token account of another mint, output mint token account of another owner
+    for (receiver_output_mint_token_account, error_code) in [
+        (env.taker_token_a_account, anchor_lang::error::ErrorCode::ConstraintTokenMint),
+        (Some(env.fee_recipient_token_b_account), anchor_lang::error::ErrorCode::ConstraintTokenOwner),
+    ] {
+        let result = process_instructions(
+            &[create_record_output_balance_instruction(env.taker, env.taker, env.token_b_mint, receiver_output_mint_token_account)],
+            &env.payer,
+            &[&env.taker_keypair],
+            &env.banks_client,
+        ).await;
+        assert_expected_error(result, Some(&TransactionError::InstructionError(
+            0, solana_sdk::instruction::InstructionError::Custom(error_code.into())
+        )));
+    }
+}
+
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, prefer_bpf: true, ..Default::default()}, 45_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, prefer_bpf: true, ..Default::default()}, 40_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, prefer_bpf: true, ..Default::default()}, 40_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, prefer_bpf: true, ..Default::default()}, 45_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, prefer_bpf: true, ..Default::default()}, 70_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, prefer_bpf: true, ..Default::default()}, 70_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_wsol_vault: true, prefer_bpf: true, ..Default::default()}, 45_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_wsol_vault: true, prefer_bpf: true, ..Default::default()}, 45_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, prefer_bpf: true, ..Default::default()}, 45_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, prefer_bpf: true, ..Default::default()}, 45_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, prefer_bpf: true, ..Default::default()}, 45_000)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::Token }, input_mint_extensions: Some(vec![ExtensionInitializationParams::TransferFeeConfig { transfer_fee_config_authority: None, withdraw_withheld_authority: None, transfer_fee_basis_points: 100, maximum_fee: u64::MAX }]), prefer_bpf: true, ..Default::default()}, 60_000)]
+#[test_case(TestMode { use_shared_taker: true, prefer_bpf: true, ..Default::default()}, 45_000)]
+#[tokio::test]
+async fn test_swap_compute_units(test_mode: TestMode, max_compute_units: u64) {
+    // CU ceilings per path of the compiled program (`cargo build-sbf` first), a regression fails here.
+    // Lower them when a path gets cheaper
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    // receiver token account is created beforehand, only the swap instruction is measured
+    process_and_assert_ok(&all_instructions[..1], &env.payer, &[], &env.banks_client).await;
+    if test_mode.use_shared_taker {
+        mint_balance(test_mode.input_amounts[0], env.shared_token_a_account, &env.token_a, AccountKind::Token, &env.banks_client, &env.payer).await;
+    }
+    let compute_units = simulate_compute_units(
+        &all_instructions[1..],
+        &env.payer,
+        &env.taker_keypair,
+        &env.makers_keypairs[..1],
+        &env.banks_client,
+    )
+    .await;
+    println!("Swap compute units: {}", compute_units);
+    assert!(compute_units <= max_compute_units, "{} CU > {} CU ceiling", compute_units, max_compute_units);
+}
+
+#[test_case(TestMode { input_amounts: vec![1_000_000_000, 3_000_000_000], output_amounts: vec![2_000_000_000, 6_000_000_000], ..Default::default()}, false)]
+#[test_case(TestMode { receiver_kind: ReceiverKind::AnotherAddress, fee_bps: 30, ..Default::default()}, false)]
+#[test_case(TestMode { expired_output_tier: true, ..Default::default()}, false)]
+#[test_case(TestMode { use_shared_taker: true, ..Default::default()}, false)]
+#[test_case(TestMode { use_shared_taker: true, ..Default::default()}, true)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, ..Default::default()}, false)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, ..Default::default()}, false)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, ..Default::default()}, false)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, ..Default::default()}, false)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::NativeMint, output: AccountKind::Token }, maker_accounts: Accounts { input: AccountKind::NativeSol, output: AccountKind::Token }, ..Default::default()}, false)]
+#[test_case(TestMode { taker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeMint }, maker_accounts: Accounts { input: AccountKind::Token, output: AccountKind::NativeSol }, ..Default::default()}, false)]
+#[tokio::test]
+async fn test_swap_events(test_mode: TestMode, partial_fill: bool) {
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    // shared taker swaps its whole balance, half of the quote is a partial fill
+    let taker_balance = if partial_fill { test_mode.input_amounts[0] / 2 } else { test_mode.input_amounts[0] };
+    if test_mode.use_shared_taker {
+        mint_balance(taker_balance, env.shared_token_a_account, &env.token_a, AccountKind::Token, &env.banks_client, &env.payer).await;
+    }
+    let fills = simulate_swap_fills(
+        all_instructions.as_slice(),
+        &env.payer,
+        &env.taker_keypair,
+        &env.makers_keypairs[..test_mode.input_amounts.len()],
+        &env.banks_client,
+    )
+    .await;
+
+    let transfer_kind = |from: &AccountKind, to: &AccountKind| TransferKind::new(*from != AccountKind::NativeSol, *to != AccountKind::NativeSol);
+    assert_eq!(fills.len(), test_mode.input_amounts.len());
+    for (i, fill) in fills.iter().enumerate() {
+        let filled_taker_amount = if i == 0 { taker_balance } else { test_mode.input_amounts[i] };
+        let filled_maker_amount = test_mode.output_amounts[i] * filled_taker_amount / test_mode.input_amounts[i];
+        // receiver token account creation goes first
+        assert_eq!(fill.instruction_index as usize, i + 1);
+        assert_eq!(fill.taker, if test_mode.use_shared_taker { FillTaker::SharedAccount(SharedAccountSeeds { owner: env.taker, route_id: 0 }) } else { FillTaker::Wallet(env.taker) });
+        assert_eq!(fill.receiver, if test_mode.receiver_kind == ReceiverKind::AnotherAddress { env.random_receiver } else { env.taker });
+        assert_eq!(fill.maker, env.makers[i]);
+        assert_eq!(fill.taker_token, env.token_a_mint);
+        assert_eq!(fill.maker_token, env.token_b_mint);
+        assert_eq!(fill.filled_taker_amount, filled_taker_amount);
+        assert_eq!(fill.filled_maker_amount, filled_maker_amount);
+        assert_eq!(fill.fee_amount, filled_maker_amount * test_mode.fee_bps as u64 / 10_000);
+        assert_eq!(fill.details, Some(SwapFillDetails {
+            input_amount: test_mode.input_amounts[i],
+            output_tier: if test_mode.expired_output_tier { 1 } else { 0 },
+            output_amount: AmountWithExpiry { amount: test_mode.output_amounts[i], expiry: u64::MAX },
+            partial_fill,
+            input_transfer: transfer_kind(&test_mode.taker_accounts.input, &test_mode.maker_accounts.input),
+            output_transfer: transfer_kind(&test_mode.maker_accounts.output, &test_mode.taker_accounts.output),
+            positive_slippage_amount: 0,
+            positive_slippage_output_amount: 0,
+            relayer_fee_amount: 0,
+        }));
+    }
+}
+
+#[test]
+fn test_decode_logged_swap_fills() {
+    // unversioned program logged the event with emit!, swap goes after another program's instruction
+    let (taker, maker, receiver, other_program) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let (taker_token, maker_token) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let account_keys = [taker, maker, receiver, other_program, bebop_rfq::ID];
+    let instructions = [
+        CompiledInstruction::new_from_raw_parts(3, vec![], vec![]),
+        CompiledInstruction::new_from_raw_parts(4, vec![], vec![0, 1, 2]),
+    ];
+    let mut event_data = BebopSwap::DISCRIMINATOR.to_vec();
+    (7u64, maker, taker_token, maker_token, 1_000_000_000u64, 2_000_000_000u64).serialize(&mut event_data).unwrap();
+    let log_messages = [
+        format!("Program {} invoke [1]", other_program),
+        format!("Program {} success", other_program),
+        format!("Program {} invoke [1]", bebop_rfq::ID),
+        "Program log: Instruction: Swap".to_string(),
+        format!("Program data: {}", STANDARD.encode(&event_data)),
+        format!("Program {} consumed 20000 of 200000 compute units", bebop_rfq::ID),
+        format!("Program {} success", bebop_rfq::ID),
+    ];
+    let fills = decode_logged_swap_fills(&account_keys, &instructions, &[], &log_messages).unwrap();
+    assert_eq!(fills, vec![SwapFill {
+        instruction_index: 1,
+        event_id: 7,
+        taker: FillTaker::Wallet(taker),
+        receiver,
+        maker,
+        taker_token,
+        maker_token,
+        filled_taker_amount: 1_000_000_000,
+        filled_maker_amount: 2_000_000_000,
+        fee_amount: 0,
+        details: None,
+    }]);
+}
+
+#[test]
+fn test_decode_newer_event_version() {
+    // event of an upgraded program is skipped instead of failing the transaction
+    let (taker, maker, receiver) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let account_keys = [taker, maker, receiver, bebop_rfq::ID, event_authority_address()];
+    let swap_instruction = CompiledInstruction::new_from_raw_parts(3, vec![], vec![0, 0, 1, 2]);
+    let mut event_data = EVENT_IX_TAG_LE.to_vec();
+    event_data.extend(BebopSwap::DISCRIMINATOR);
+    event_data.push(EVENT_VERSION + 1);
+    event_data.extend([0; 64]);
+    let inner_instructions = [InnerInstructions {
+        index: 0,
+        instructions: vec![CompiledInstruction::new_from_raw_parts(3, event_data, vec![4])],
+    }];
+    assert_eq!(decode_swap_fills(&account_keys, &[swap_instruction], &inner_instructions), Ok(vec![]));
+}
+
+fn route_rfq_hop(input_mint: Pubkey, output_mint: Pubkey, maker_output: ClientAccountKind) -> Hop {
+    Hop::Rfq(RfqHop {
+        maker: Pubkey::new_unique(),
+        input_mint,
+        input_token_program: spl_token::ID,
+        output_mint,
+        output_token_program: spl_token::ID,
+        maker_output,
+        input_amount: 1_000_000_000,
+        output_amounts: vec![AmountWithExpiry { amount: 1_000_000_000, expiry: u64::MAX }],
+        ..Default::default()
+    })
+}
+
+fn route_pool_hop(input_mint: Pubkey, output_mint: Pubkey) -> Hop {
+    Hop::Pool(PoolHop {
+        input_mint,
+        input_token_program: spl_token::ID,
+        output_mint,
+        output_token_program: spl_token::ID,
+        instruction: Box::new(|accounts: &PoolAccounts| Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![
+                AccountMeta::new_readonly(accounts.owner, true),
+                AccountMeta::new(accounts.input_token_account, false),
+                AccountMeta::new(accounts.output_token_account, false),
+            ],
+        )),
+    })
+}
+
+#[test]
+fn test_route_pool_then_maker() {
+    // README order type 4: pool output goes to shared account, which swaps with the maker
+    let (taker, payer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (token_a, token_c, token_b) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let shared_account = shared_account_address(&taker, 7).0;
+    let instructions = Route::new(taker, payer)
+        .route_id(7)
+        .close_shared_accounts(true)
+        .hop(route_pool_hop(token_a, token_c))
+        .hop(route_rfq_hop(token_c, token_b, ClientAccountKind::Token))
+        .instructions()
+        .unwrap();
+    // shared token_c and taker token_b ATAs, pool swap, maker swap, shared token_c sweep
+    assert_eq!(instructions.len(), 5);
+    let shared_token_c_account = get_associated_token_address_with_program_id(&shared_account, &token_c, &spl_token::ID);
+    assert_eq!(instructions[2].accounts[2].pubkey, shared_token_c_account);
+    let swap = &instructions[3];
+    assert_eq!(swap.accounts[1].pubkey, shared_account);
+    assert!(!swap.accounts[1].is_signer);
+    assert_eq!(swap.accounts[4].pubkey, shared_token_c_account);
+    assert_eq!(instructions[4], sweep_shared_instruction(taker, 7, Some((token_c, spl_token::ID)), true));
+}
+
+#[test]
+fn test_route_invalid_topology() {
+    let (taker, payer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (token_a, token_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let wsol = spl_token::native_mint::ID;
+
+    assert_eq!(Route::new(taker, payer).instructions().unwrap_err(), ClientError::EmptyRoute);
+    assert_eq!(
+        Route::new(taker, payer)
+            .hop(route_rfq_hop(token_a, wsol, ClientAccountKind::NativeMint))
+            .hop(route_rfq_hop(token_a, token_b, ClientAccountKind::Token))
+            .instructions()
+            .unwrap_err(),
+        ClientError::RouteMintMismatch(1)
+    );
+    // maker unwraps SOL into the shared account
+    assert_eq!(
+        Route::new(taker, payer)
+            .hop(route_rfq_hop(token_a, wsol, ClientAccountKind::NativeSol))
+            .hop(route_rfq_hop(wsol, token_b, ClientAccountKind::Token))
+            .instructions()
+            .unwrap_err(),
+        ClientError::SharedAccountNativeSol(0)
+    );
+    // shared account as route receiver of native SOL
+    assert_eq!(
+        Route::new(taker, payer)
+            .receiver(shared_account_address(&taker, 0).0)
+            .receiver_output(ClientAccountKind::NativeSol)
+            .hop(route_rfq_hop(token_a, wsol, ClientAccountKind::NativeMint))
+            .instructions()
+            .unwrap_err(),
+        ClientError::SharedAccountNativeSol(0)
+    );
+    assert_eq!(
+        Route::new(taker, payer)
+            .taker_input(ClientAccountKind::NativeSol)
+            .hop(route_pool_hop(wsol, token_b))
+            .instructions()
+            .unwrap_err(),
+        ClientError::PoolNativeSol(0)
+    );
+}
+
+#[tokio::test]
+async fn test_swap_5_makers_with_lookup_table() {
+    // 5 maker legs overflow a legacy transaction, v0 transaction loads every non-signer from Bebop lookup table
+    let test_mode = TestMode { input_amounts: vec![1_000_000_000; 5], output_amounts: vec![2_000_000_000; 5], ..Default::default() };
+    let env: TestEnvironment = prepare_test(test_mode.clone()).await;
+    let all_instructions = env.create_single_swap_instructions(test_mode.clone(), true).await;
+    process_and_assert_ok(&all_instructions[..1], &env.payer, &[], &env.banks_client).await;
+    let swap_instructions = &all_instructions[1..];
+
+    let legacy_tx = Transaction::new_unsigned(Message::new(swap_instructions, Some(&env.payer.pubkey())));
+    assert!(bincode::serialize(&legacy_tx).unwrap().len() > PACKET_DATA_SIZE);
+
+    let mut addresses = bebop_lookup_table_addresses(&[
+        (env.token_a_mint, env.token_a_program_id),
+        (env.token_b_mint, env.token_b_program_id),
+    ]);
+    for meta in swap_instructions.iter().flat_map(|instruction| instruction.accounts.iter()) {
+        if !meta.is_signer && !addresses.contains(&meta.pubkey) {
+            addresses.push(meta.pubkey);
         }
     }
-    balance_checker.verify_balances_for_swap_then_onchain(&env, test_mode, onchain_pool_output_token_c).await;
-}
\ No newline at end of file
+    let lookup_table = create_test_lookup_table(&env, &addresses).await;
+
+    let balance_checker: BalanceChecker = BalanceChecker::new(&env).await;
+    let result = sign_and_execute_v0_tx(
+        swap_instructions,
+        &env.payer,
+        &env.taker_keypair,
+        &env.makers_keypairs,
+        &[lookup_table],
+        &env.banks_client,
+    )
+    .await;
+    assert_matches!(result, Ok(()));
+    balance_checker.verify_balances_direct_swap(&env, test_mode).await;
+}
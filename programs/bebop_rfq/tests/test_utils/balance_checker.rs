@@ -21,6 +21,8 @@ use spl_token_client::{
     },
     token::{ExtensionInitializationParams, Token},
 };
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFee;
+use bebop_rfq::bebop_rfq::TransferFeeMode;
 use crate::test_utils::{AccountKind, ReceiverKind};
 
 use super::{TestEnvironment, TestMode};
@@ -42,6 +44,8 @@ pub struct BalanceChecker {
     receiver_balances: Balances,
     shared_pda_balances: Balances,
     makers_balances: Vec<Balances>,
+    fee_recipient_balance: u64,
+    relayer_fee_balance: u64,
 }
 
 impl BalanceChecker {
@@ -64,39 +68,88 @@ impl BalanceChecker {
                 if env.makers_token_c_account.is_empty() {None} else {Some(env.makers_token_c_account[i])},
             ).await);
         }
+        let fee_recipient_balance = BalanceReader::new(
+            &env.token_b, env.fee_recipient, &Some(env.fee_recipient_token_b_account)
+        ).get_balance().await;
+        let relayer_fee_token_account = Some(env.token_b.get_associated_token_address(&env.payer.pubkey()));
+        let relayer_fee_balance = BalanceReader::new(
+            &env.token_b, env.payer.pubkey(), &relayer_fee_token_account
+        ).get_balance().await;
         Self {
             taker_balances,
             receiver_balances,
             shared_pda_balances,
             makers_balances,
+            fee_recipient_balance,
+            relayer_fee_balance,
         }
     }
 
+    fn fee_amounts(test_mode: &TestMode) -> Vec<u64> {
+        test_mode.output_amounts.iter()
+            .map(|amount| ((*amount as u128 * test_mode.fee_bps as u128) / 10_000) as u64)
+            .collect()
+    }
+
+    /// Mirrors program `get_transfer_amounts`: (sent, received) for a transfer of `amount`
+    fn transfer_amounts(mint_extensions: &Option<Vec<ExtensionInitializationParams>>, amount: u64, transfer_fee_mode: TransferFeeMode) -> (u64, u64) {
+        let Some(transfer_fee) = mint_extensions.iter().flatten().find_map(|extension| match extension {
+            ExtensionInitializationParams::TransferFeeConfig { transfer_fee_basis_points, maximum_fee, .. } => Some(TransferFee {
+                epoch: 0.into(),
+                maximum_fee: (*maximum_fee).into(),
+                transfer_fee_basis_points: (*transfer_fee_basis_points).into(),
+            }),
+            _ => None,
+        }) else {
+            return (amount, amount);
+        };
+        let sent_amount = match transfer_fee_mode {
+            TransferFeeMode::Gross => amount,
+            TransferFeeMode::Net => amount + transfer_fee.calculate_inverse_fee(amount).unwrap(),
+        };
+        (sent_amount, sent_amount - transfer_fee.calculate_fee(sent_amount).unwrap())
+    }
+
     pub async fn verify_balances_direct_swap(&self, env: &TestEnvironment, test_mode: TestMode){
         let new_balances: BalanceChecker = Self::new(env).await;
+        let fee_amounts = Self::fee_amounts(&test_mode);
+        let input_transfers: Vec<(u64, u64)> = test_mode.input_amounts.iter()
+            .map(|amount| Self::transfer_amounts(&test_mode.input_mint_extensions, *amount, test_mode.transfer_fee_mode))
+            .collect();
+        let relayer_fee_amount = test_mode.relayer_fee.map_or(0, |relayer_fee| relayer_fee.amount);
+        let receiver_transfers: Vec<(u64, u64)> = test_mode.output_amounts.iter().zip(fee_amounts.iter())
+            .map(|(amount, fee)| Self::transfer_amounts(
+                &test_mode.output_mint_extensions, amount - fee - relayer_fee_amount, test_mode.transfer_fee_mode
+            ))
+            .collect();
+        let fee_transfers: Vec<(u64, u64)> = fee_amounts.iter()
+            .map(|fee| Self::transfer_amounts(&test_mode.output_mint_extensions, *fee, test_mode.transfer_fee_mode))
+            .collect();
+        let relayer_fee_transfer = Self::transfer_amounts(&test_mode.output_mint_extensions, relayer_fee_amount, test_mode.transfer_fee_mode);
+        let received_amount: u64 = receiver_transfers.iter().map(|(_, received)| received).sum();
         
         // Verify taker balances
         assert_eq!(
             self.taker_balances.token_a.checked_sub(new_balances.taker_balances.token_a),
-            Some(test_mode.input_amounts.iter().sum())
+            Some(input_transfers.iter().map(|(sent, _)| sent).sum())
         );
         match test_mode.receiver_kind {
             ReceiverKind::Taker | ReceiverKind::TakerWithTokenAccount => {
                 assert_eq!(
                     new_balances.taker_balances.token_b.checked_sub(self.taker_balances.token_b),
-                    Some(test_mode.output_amounts.iter().sum())
+                    Some(received_amount)
                 );
             },
             ReceiverKind::AnotherAddress => {
                 assert_eq!(
                     new_balances.receiver_balances.token_b.checked_sub(self.receiver_balances.token_b),
-                    Some(test_mode.output_amounts.iter().sum())
+                    Some(received_amount)
                 );
             },
             ReceiverKind::SharedAccount => {
                 assert_eq!(
                     new_balances.shared_pda_balances.token_b.checked_sub(self.shared_pda_balances.token_b),
-                    Some(test_mode.output_amounts.iter().sum())
+                    Some(received_amount)
                 );
             },
         }
@@ -113,15 +166,29 @@ impl BalanceChecker {
         assert_eq!(new_balances.shared_pda_balances.token_c, self.shared_pda_balances.token_c);
         assert_eq!(new_balances.shared_pda_balances.native, self.shared_pda_balances.native);
 
+        // Verify fee recipient balance
+        assert_eq!(
+            new_balances.fee_recipient_balance.checked_sub(self.fee_recipient_balance),
+            Some(fee_transfers.iter().map(|(_, received)| received).sum())
+        );
+
+        // Verify relayer fee balance, lamports reimbursement is hidden by transaction fees
+        if test_mode.maker_accounts.output != AccountKind::NativeSol {
+            assert_eq!(
+                new_balances.relayer_fee_balance.checked_sub(self.relayer_fee_balance),
+                Some(relayer_fee_transfer.1 * test_mode.output_amounts.len() as u64)
+            );
+        }
+
         // Verify makers balances
         for i in 0..test_mode.input_amounts.len() {
             assert_eq!(
                 new_balances.makers_balances[i].token_a.checked_sub(self.makers_balances[i].token_a),
-                Some(test_mode.input_amounts[i])
+                Some(input_transfers[i].1)
             );
             assert_eq!(
                 self.makers_balances[i].token_b.checked_sub(new_balances.makers_balances[i].token_b),
-                Some(test_mode.output_amounts[i])
+                Some(receiver_transfers[i].0 + fee_transfers[i].0 + relayer_fee_transfer.0)
             );
             assert_eq!(new_balances.makers_balances[i].token_c, self.makers_balances[i].token_c);
         }
@@ -181,7 +248,7 @@ impl BalanceChecker {
         // Verify maker-1 balance
         assert_eq!(
             new_balances.makers_balances[0].token_a.checked_sub(self.makers_balances[0].token_a),
-            Some(test_mode.input_amounts[0])
+            Some(Self::transfer_amounts(&test_mode.input_mint_extensions, test_mode.input_amounts[0], test_mode.transfer_fee_mode).1)
         );
         assert_eq!(
             self.makers_balances[0].token_c.checked_sub(new_balances.makers_balances[0].token_c),
@@ -215,6 +282,13 @@ impl BalanceChecker {
         &self, env: &TestEnvironment, test_mode: TestMode, onchain_input_amount: u64, onchain_output_amount: u64, final_output_amount: u64
     ){
         let new_balances: BalanceChecker = Self::new(env).await;
+        // with surplus_to_receiver maker gets at most the quoted input, the rest goes to the receiver
+        let maker_input_amount = if test_mode.surplus_to_receiver {
+            onchain_output_amount.min(test_mode.input_amounts.iter().sum())
+        } else {
+            onchain_output_amount
+        };
+        let surplus_amount = onchain_output_amount - maker_input_amount;
         
         // Verify taker balances
         assert_eq!(
@@ -241,10 +315,17 @@ impl BalanceChecker {
                 );
             },
         }
-        assert_eq!(new_balances.taker_balances.token_a, self.taker_balances.token_a);
+        let receiver_is_taker = matches!(test_mode.receiver_kind, ReceiverKind::Taker | ReceiverKind::TakerWithTokenAccount);
+        assert_eq!(
+            new_balances.taker_balances.token_a.checked_sub(self.taker_balances.token_a),
+            Some(if receiver_is_taker { surplus_amount } else { 0 })
+        );
 
         // Verify receiver balances
-        assert_eq!(new_balances.receiver_balances.token_a, self.receiver_balances.token_a);
+        assert_eq!(
+            new_balances.receiver_balances.token_a.checked_sub(self.receiver_balances.token_a),
+            Some(if test_mode.receiver_kind == ReceiverKind::AnotherAddress { surplus_amount } else { 0 })
+        );
         assert_eq!(new_balances.receiver_balances.token_c, self.receiver_balances.token_c);
         assert_eq!(new_balances.receiver_balances.native, self.receiver_balances.native);
 
@@ -257,7 +338,7 @@ impl BalanceChecker {
         // Verify maker-1 balances
         assert_eq!(
             new_balances.makers_balances[0].token_a.checked_sub(self.makers_balances[0].token_a),
-            Some(onchain_output_amount)
+            Some(maker_input_amount)
         );
         assert_eq!(
             self.makers_balances[0].token_b.checked_sub(new_balances.makers_balances[0].token_b),
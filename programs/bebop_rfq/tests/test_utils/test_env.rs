@@ -3,19 +3,20 @@ use std::{any::Any, sync::Arc};
 
 use anchor_lang::{
     prelude::*,
-    solana_program::{self, instruction::Instruction},
+    solana_program::{self, bpf_loader_upgradeable::{self, UpgradeableLoaderState}, instruction::Instruction},
     system_program, InstructionData,
 };
-use anchor_spl::{associated_token::spl_associated_token_account::instruction, token::{self, spl_token::{instruction::sync_native, native_mint}}};
+use anchor_spl::{associated_token::spl_associated_token_account::instruction, token::{self, spl_token::native_mint}, token_2022::spl_token_2022::{self, instruction::sync_native}};
 use assert_matches::assert_matches;
-use bebop_rfq::bebop_rfq::AmountWithExpiry;
+use bebop_rfq_client::{compile_v0_message, create_bebop_lookup_table, decode_swap_fills, event_authority_address, extend_bebop_lookup_table, init_maker_wsol_vault_instruction, lookup_table_account, maker_authority_address, maker_risk_state_address, maker_wsol_vault_address, pda_bumps, set_delegate_instruction, set_risk_limit_instruction, taker_authority_address, shared_account_address, Hop, InnerInstructions, RfqHop, Route, SwapBuilder, SwapFill, Taker};
+use bebop_rfq::bebop_rfq::{AmountWithExpiry, BatchLeg, DelegateLimits, Order, PositiveSlippage, RelayerFee, SharedAccountSeeds, TransferFeeMode};
 use itertools::Itertools;
 use solana_program_test::{
     tokio::{self, sync::Mutex},
-    BanksClient, BanksClientError, ProgramTest,
+    BanksClient, BanksClientError, ProgramTest, ProgramTestContext,
 };
 use solana_sdk::{
-    feature_set::bpf_account_data_direct_mapping, message::Message, native_token::LAMPORTS_PER_SOL, signature::{Keypair, Signature}, signer::Signer, system_instruction, transaction::{Transaction, TransactionError}
+    account::AccountSharedData, address_lookup_table::AddressLookupTableAccount, feature_set::bpf_account_data_direct_mapping, message::Message, native_token::LAMPORTS_PER_SOL, packet::PACKET_DATA_SIZE, signature::{Keypair, Signature}, signer::Signer, system_instruction, sysvar::slot_hashes::SlotHashes, transaction::{Transaction, TransactionError, VersionedTransaction}
 };
 use spl_token_client::{
     client::{
@@ -26,15 +27,21 @@ use spl_token_client::{
 };
 
 pub struct TestEnvironment {
+    pub context: Arc<Mutex<ProgramTestContext>>, // for slot warps, banks_client is its clone
     pub banks_client: Arc<Mutex<BanksClient>>,
     pub payer: Arc<Keypair>,
     pub taker_keypair: Keypair,
     pub makers_keypairs: Vec<Keypair>,
+    /// Hot key of every maker when `TestMode::delegate_limits` is set
+    pub delegate_keypair: Keypair,
 
     pub makers: Vec<Pubkey>,
     pub taker: Pubkey,
     pub random_receiver: Pubkey,
     pub shared_pda: Pubkey,
+    pub config: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub fee_recipient_token_b_account: Pubkey,
 
     pub taker_token_a_account: Option<Pubkey>,
     pub makers_token_a_account: Vec<Pubkey>,  // empty array means None for all
@@ -62,20 +69,26 @@ pub struct TestEnvironment {
     pub token_c_program_id: Pubkey,
 
     pub temporary_wsol_token_accounts: Vec<Pubkey>, // empty array means None for all
+    pub transfer_hook_accounts: Vec<AccountMeta>, // mock transfer-hook program and ExtraAccountMetaList of hooked mints
 }
 
 
 impl TestEnvironment {
 
+    pub async fn get_config(&self) -> bebop_rfq::state::Config {
+        let account = self.banks_client.lock().await.get_account(self.config).await.unwrap().unwrap();
+        bebop_rfq::state::Config::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
     pub async fn create_single_swap_instructions(&self, test_mode: TestMode, mint_taker_balance: bool) -> Vec<Instruction> {
         // token_a -> token_b swap (taker pov)
 
         let TestEnvironment {
+            transfer_hook_accounts,
             banks_client,
             makers,
             taker,
             random_receiver,
-            temporary_wsol_token_accounts,
             payer,
             shared_pda,
             token_a,
@@ -85,28 +98,23 @@ impl TestEnvironment {
             token_b_mint,
             token_b_program_id,
 
-            shared_token_a_account,
             taker_token_a_account,
-            makers_token_a_account,
-
-            taker_token_b_account,
-            receiver_token_b_account,
-            shared_token_b_account,
             makers_token_b_account,
+            fee_recipient_token_b_account,
 
             ..
         } = self;
 
-        let (cur_receiver_address, cur_receiver_token_b_account) = match test_mode.receiver_kind {
-            ReceiverKind::Taker => (taker, taker_token_b_account),
+        let cur_receiver_address = match test_mode.receiver_kind {
+            ReceiverKind::Taker => taker,
             ReceiverKind::TakerWithTokenAccount => {
                 get_associated_token_account(
                     *taker, &token_b, test_mode.taker_accounts.output.clone(), true
                 ).await;
-                (taker, taker_token_b_account)
+                taker
             }
-            ReceiverKind::AnotherAddress => (random_receiver, receiver_token_b_account),
-            ReceiverKind::SharedAccount => (shared_pda, shared_token_b_account),
+            ReceiverKind::AnotherAddress => random_receiver,
+            ReceiverKind::SharedAccount => shared_pda,
         };
         let mut instructions = Vec::new();
         if test_mode.receiver_kind != ReceiverKind::TakerWithTokenAccount {
@@ -114,59 +122,104 @@ impl TestEnvironment {
                 &payer.pubkey(), cur_receiver_address, token_b_mint, token_b_program_id
             ));
         }
+        if test_mode.surplus_to_receiver {
+            instructions.push(instruction::create_associated_token_account_idempotent(
+                &payer.pubkey(), cur_receiver_address, token_a_mint, token_a_program_id
+            ));
+        }
+        if test_mode.relayer_fee.is_some() && test_mode.maker_accounts.output != AccountKind::NativeSol {
+            instructions.push(instruction::create_associated_token_account_idempotent(
+                &payer.pubkey(), &payer.pubkey(), token_b_mint, token_b_program_id
+            ));
+        }
         if mint_taker_balance {
-            mint_balance(test_mode.input_amounts.iter().sum(), *taker_token_a_account,
+            mint_balance(test_mode.mint_amount(test_mode.input_amounts.iter().sum()), *taker_token_a_account,
          token_a, test_mode.clone().taker_accounts.input, banks_client, payer).await;
         }
+        // positive slippage can at most double the maker output
+        let maker_mint_multiplier = if test_mode.positive_slippage.is_some() { 2 } else { 1 };
         for (i, amount) in test_mode.output_amounts.iter().enumerate() {
-            mint_balance(*amount, if makers_token_b_account.is_empty() { None } else { makers_token_b_account.get(i).cloned() },
+            mint_balance(test_mode.mint_amount(*amount * maker_mint_multiplier), if makers_token_b_account.is_empty() { None } else { makers_token_b_account.get(i).cloned() },
              token_b, test_mode.clone().maker_accounts.output, banks_client, payer).await;
         }
 
+        if let (true, Some(taker_token_a_account)) = (test_mode.signed_taker_order, taker_token_a_account) {
+            token_a.approve(
+                taker_token_a_account, &taker_authority_address(), taker,
+                test_mode.mint_amount(test_mode.input_amounts.iter().sum()), &[&self.taker_keypair]
+            ).await.unwrap();
+        }
+
         for i in 0..test_mode.input_amounts.len() {
             assert_eq!(test_mode.input_amounts.len(), test_mode.output_amounts.len());
-            
-            let data = bebop_rfq::instruction::Swap {
-                input_amount: test_mode.input_amounts[i],
-                output_amounts: vec![ AmountWithExpiry {
-                    amount: test_mode.output_amounts[i],
-                    expiry: u64::MAX,
-                }],
-                event_id: 0
-            }
-            .data();
 
-            let accs = bebop_rfq::accounts::Swap {
-                maker: makers[i],
-                taker: if test_mode.use_shared_taker {*shared_pda} else {*taker},
-                receiver: *cur_receiver_address,
-                taker_input_mint_token_account: if test_mode.use_shared_taker {*shared_token_a_account} else {*taker_token_a_account},
-                maker_input_mint_token_account: if makers_token_a_account.is_empty() { None } else { makers_token_a_account.get(i).cloned() },
-                receiver_output_mint_token_account: *cur_receiver_token_b_account,
-                maker_output_mint_token_account: if makers_token_b_account.is_empty() { None } else { makers_token_b_account.get(i).cloned() },
-                input_mint: *token_a_mint,
-                input_token_program: *token_a_program_id,
-                output_mint: *token_b_mint,
-                output_token_program: *token_b_program_id,
-                system_program: system_program::ID,
+            let taker = if test_mode.use_shared_taker {
+                Taker::SharedAccount(SharedAccountSeeds { owner: *taker, route_id: 0 })
+            } else if test_mode.signed_taker_order {
+                Taker::SignedOrder(*taker)
+            } else {
+                Taker::Wallet(*taker)
             };
-            let mut instruction = Instruction {
-                program_id: bebop_rfq::ID,
-                accounts: accs.to_account_metas(None),
-                data,
-            };
-            if !test_mode.use_shared_taker {
-                instruction
-                    .accounts
+            let mut output_amounts = vec![AmountWithExpiry { amount: test_mode.output_amounts[i], expiry: u64::MAX }];
+            if test_mode.expired_output_tier {
+                output_amounts.insert(0, AmountWithExpiry { amount: test_mode.output_amounts[i] * 2, expiry: 1 });
+            }
+            let mut builder = SwapBuilder::new(taker, makers[i], payer.pubkey())
+                .receiver(*cur_receiver_address)
+                .input_mint(*token_a_mint, *token_a_program_id, test_mode.taker_accounts.input.clone().into(), test_mode.maker_accounts.input.clone().into())
+                .output_mint(*token_b_mint, *token_b_program_id, test_mode.maker_accounts.output.clone().into(), test_mode.taker_accounts.output.clone().into())
+                .amounts(test_mode.input_amounts[i], output_amounts)
+                .nonce(test_mode.nonce)
+                .taker_nonce(test_mode.nonce + i as u64)
+                .transfer_fee_mode(test_mode.transfer_fee_mode)
+                .transfer_hook_accounts(transfer_hook_accounts.clone())
+                .surplus_to_receiver(test_mode.surplus_to_receiver)
+                .maker_wsol_vault(test_mode.maker_wsol_vault);
+            if test_mode.fee_bps > 0 {
+                builder = builder.fee(test_mode.fee_bps, *fee_recipient_token_b_account);
+            }
+            if let Some(positive_slippage) = test_mode.positive_slippage {
+                builder = builder.positive_slippage(positive_slippage);
+            }
+            if let Some(relayer_fee) = test_mode.relayer_fee {
+                builder = builder.relayer_fee(relayer_fee);
+            }
+            if let Some(delegate_limits) = &test_mode.delegate_limits {
+                let mut pair_mints = [*token_a_mint, *token_b_mint].into_iter();
+                let mut delegate_limits = delegate_limits.clone();
+                delegate_limits.mints
                     .iter_mut()
-                    .for_each(|account| if account.pubkey == *taker { account.is_signer = true });
+                    .filter(|limit| limit.mint == Pubkey::default())
+                    .for_each(|limit| limit.mint = pair_mints.next().unwrap());
+                process_and_assert_ok(
+                    &[set_delegate_instruction(makers[i], self.delegate_keypair.pubkey(), delegate_limits)],
+                    payer,
+                    &[&self.makers_keypairs[i]],
+                    banks_client,
+                ).await;
+                if let Some(maker_token_b_account) = makers_token_b_account.get(i) {
+                    token_b.approve(
+                        maker_token_b_account, &maker_authority_address(), &makers[i],
+                        test_mode.mint_amount(test_mode.output_amounts[i] * maker_mint_multiplier), &[&self.makers_keypairs[i]]
+                    ).await.unwrap();
+                }
+                builder = builder.delegate(self.delegate_keypair.pubkey());
             }
-            if temporary_wsol_token_accounts.len() > 0 {
-                instruction
-                    .accounts
-                    .push(AccountMeta::new(temporary_wsol_token_accounts[i].clone(), false));
+            if let Some((window_slots, max_volume)) = test_mode.risk_limit {
+                process_and_assert_ok(
+                    &[set_risk_limit_instruction(makers[i], *token_a_mint, *token_b_mint, window_slots, max_volume)],
+                    payer,
+                    &[&self.makers_keypairs[i]],
+                    banks_client,
+                ).await;
             }
-            instructions.push(instruction);
+            if test_mode.signed_taker_order {
+                instructions.push(new_ed25519_instruction(&self.taker_keypair, &builder.taker_order().unwrap().hash()));
+                if let Some(tampered_amount) = test_mode.tampered_output_amount {
+                    builder = builder.amounts(test_mode.input_amounts[i], vec![AmountWithExpiry { amount: tampered_amount, expiry: u64::MAX }]);
+                }
+            }
+            instructions.push(builder.build().unwrap());
         }
         instructions
     }
@@ -177,11 +230,11 @@ impl TestEnvironment {
         // token_c <-> token_b (taker=shared_pda, maker=maker2, receiver=taker)
 
         let TestEnvironment {
+            transfer_hook_accounts,
             banks_client,
             makers,
             taker,
             random_receiver,
-            temporary_wsol_token_accounts,
             payer,
             shared_pda,
             token_a,
@@ -195,36 +248,17 @@ impl TestEnvironment {
             token_c_program_id,
 
             taker_token_a_account,
-            makers_token_a_account,
-
-            taker_token_b_account,
-            receiver_token_b_account,
             makers_token_b_account,
-            shared_token_b_account,
-
-            shared_token_c_account,
             makers_token_c_account,
 
             ..
         } = self;
 
-        let (cur_receiver_address, cur_receiver_token_b_account) = match test_mode.receiver_kind {
-            ReceiverKind::Taker => (taker, taker_token_b_account),
-            ReceiverKind::TakerWithTokenAccount => {
-                get_associated_token_account(
-                    *taker, &token_b, test_mode.taker_accounts.output.clone(), true
-                ).await;
-                (taker, taker_token_b_account)
-            }
-            ReceiverKind::AnotherAddress => (random_receiver, receiver_token_b_account),
-            ReceiverKind::SharedAccount => (shared_pda, shared_token_b_account),
+        let cur_receiver_address = match test_mode.receiver_kind {
+            ReceiverKind::Taker | ReceiverKind::TakerWithTokenAccount => taker,
+            ReceiverKind::AnotherAddress => random_receiver,
+            ReceiverKind::SharedAccount => shared_pda,
         };
-        let mut instructions = Vec::new();
-        if test_mode.receiver_kind != ReceiverKind::TakerWithTokenAccount {
-            instructions.push(instruction::create_associated_token_account(
-                &payer.pubkey(), cur_receiver_address, token_b_mint, token_b_program_id
-            ));
-        }
         let middle_amount = test_mode.middle_token_info.clone().unwrap().token_amount;
 
         mint_balance(test_mode.input_amounts.iter().sum(), *taker_token_a_account,
@@ -236,81 +270,300 @@ impl TestEnvironment {
         assert_eq!(test_mode.input_amounts.len(), 1);
         assert_eq!(test_mode.input_amounts.len(), test_mode.output_amounts.len());
 
-        let data_1 = bebop_rfq::instruction::Swap {
-            input_amount: test_mode.input_amounts[0],
-            output_amounts: vec![ AmountWithExpiry {
-                amount: middle_amount,
-                expiry: u64::MAX,
-            }],
-            event_id: 0
-        }.data();
-        let mut instruction_1 = Instruction {
-            program_id: bebop_rfq::ID,
-            accounts: bebop_rfq::accounts::Swap {
+        Route::new(*taker, payer.pubkey())
+            .receiver(*cur_receiver_address)
+            .taker_input(test_mode.taker_accounts.input.clone().into())
+            .receiver_output(test_mode.taker_accounts.output.clone().into())
+            .hop(Hop::Rfq(RfqHop {
                 maker: makers[0],
-                taker: *taker,
-                receiver: *shared_pda,
-                taker_input_mint_token_account: *taker_token_a_account,
-                maker_input_mint_token_account: if makers_token_a_account.is_empty() { None } else { makers_token_a_account.get(0).cloned() },
-                receiver_output_mint_token_account: *shared_token_c_account,
-                maker_output_mint_token_account: if makers_token_c_account.is_empty() { None } else { makers_token_c_account.get(0).cloned() },
                 input_mint: *token_a_mint,
                 input_token_program: *token_a_program_id,
+                maker_input: test_mode.maker_accounts.input.clone().into(),
                 output_mint: *token_c_mint,
                 output_token_program: *token_c_program_id,
-                system_program: system_program::ID,
-            }
-            .to_account_metas(None),
-            data: data_1,
-        };
-        instruction_1
-            .accounts
-            .iter_mut()
-            .for_each(|account| if account.pubkey == *taker { account.is_signer = true });
-        if temporary_wsol_token_accounts.len() > 0 {
-            instruction_1
-                .accounts
-                .push(AccountMeta::new(temporary_wsol_token_accounts[0].clone(), false));
-        }
-
-        let data_2 = bebop_rfq::instruction::Swap {
-            input_amount: middle_amount,
-            output_amounts: vec![AmountWithExpiry {
-                amount: test_mode.output_amounts[0],
-                expiry: u64::MAX,
-            }],
-            event_id: 0
-        }.data();
-        let mut instruction_2 = Instruction {
-            program_id: bebop_rfq::ID,
-            accounts: bebop_rfq::accounts::Swap {
+                maker_output: bebop_rfq_client::AccountKind::Token,
+                input_amount: test_mode.input_amounts[0],
+                output_amounts: vec![AmountWithExpiry { amount: middle_amount, expiry: u64::MAX }],
+                nonce: test_mode.nonce,
+                transfer_fee_mode: test_mode.transfer_fee_mode,
+                transfer_hook_accounts: transfer_hook_accounts.clone(),
+                ..Default::default()
+            }))
+            .hop(Hop::Rfq(RfqHop {
                 maker: makers[1],
-                taker: *shared_pda,
-                receiver: *cur_receiver_address,
-                taker_input_mint_token_account: *shared_token_c_account,
-                maker_input_mint_token_account: if makers_token_c_account.is_empty() { None } else { makers_token_c_account.get(1).cloned() },
-                receiver_output_mint_token_account: *cur_receiver_token_b_account,
-                maker_output_mint_token_account: if makers_token_b_account.is_empty() { None } else { makers_token_b_account.get(1).cloned() },
                 input_mint: *token_c_mint,
                 input_token_program: *token_c_program_id,
+                maker_input: bebop_rfq_client::AccountKind::Token,
                 output_mint: *token_b_mint,
                 output_token_program: *token_b_program_id,
-                system_program: system_program::ID,
-            }
-            .to_account_metas(None),
-            data: data_2,
+                maker_output: test_mode.maker_accounts.output.clone().into(),
+                input_amount: middle_amount,
+                output_amounts: vec![AmountWithExpiry { amount: test_mode.output_amounts[0], expiry: u64::MAX }],
+                nonce: test_mode.nonce,
+                transfer_fee_mode: test_mode.transfer_fee_mode,
+                transfer_hook_accounts: transfer_hook_accounts.clone(),
+                ..Default::default()
+            }))
+            .instructions()
+            .unwrap()
+    }
+
+    pub async fn create_signed_swap_instructions(&self, test_mode: TestMode) -> Vec<Instruction> {
+        // token_a -> token_b swap (taker pov), makers sign orders off-line
+
+        let TestEnvironment {
+            transfer_hook_accounts,
+            banks_client,
+            makers,
+            makers_keypairs,
+            taker,
+            random_receiver,
+            payer,
+            config,
+            token_a,
+            token_b,
+            token_a_mint,
+            token_a_program_id,
+            token_b_mint,
+            token_b_program_id,
+
+            taker_token_a_account,
+            makers_token_a_account,
+
+            taker_token_b_account,
+            receiver_token_b_account,
+            makers_token_b_account,
+            fee_recipient_token_b_account,
+
+            ..
+        } = self;
+
+        let (cur_receiver_address, cur_receiver_token_b_account) = match test_mode.receiver_kind {
+            ReceiverKind::AnotherAddress => (random_receiver, receiver_token_b_account),
+            _ => (taker, taker_token_b_account),
         };
-        if temporary_wsol_token_accounts.len() > 0 {
-            instruction_2
+        let mut instructions = vec![instruction::create_associated_token_account(
+            &payer.pubkey(), cur_receiver_address, token_b_mint, token_b_program_id
+        )];
+        mint_balance(test_mode.mint_amount(test_mode.input_amounts.iter().sum()), *taker_token_a_account,
+            token_a, test_mode.clone().taker_accounts.input, banks_client, payer).await;
+        let maker_authority = Pubkey::find_program_address(&[bebop_rfq::MAKER_AUTHORITY], &bebop_rfq::ID).0;
+
+        for i in 0..test_mode.input_amounts.len() {
+            assert_eq!(test_mode.input_amounts.len(), test_mode.output_amounts.len());
+            mint_balance(test_mode.mint_amount(test_mode.output_amounts[i]), makers_token_b_account.get(i).cloned(),
+                token_b, test_mode.clone().maker_accounts.output, banks_client, payer).await;
+            token_b.approve(
+                &makers_token_b_account[i], &maker_authority, &makers[i], test_mode.mint_amount(test_mode.output_amounts[i]), &[&makers_keypairs[i]]
+            ).await.unwrap();
+
+            let order = Order {
+                maker: makers[i],
+                taker: *taker,
+                receiver: *cur_receiver_address,
+                input_mint: *token_a_mint,
+                output_mint: *token_b_mint,
+                input_amount: test_mode.input_amounts[i],
+                output_amounts: vec![AmountWithExpiry {
+                    amount: test_mode.output_amounts[i],
+                    expiry: u64::MAX,
+                }],
+                nonce: test_mode.nonce,
+                fee_bps: test_mode.fee_bps,
+                transfer_fee_mode: test_mode.transfer_fee_mode,
+                positive_slippage: test_mode.positive_slippage,
+            };
+            instructions.push(new_ed25519_instruction(&makers_keypairs[i], &order.hash()));
+
+            let nonce_bitmap = get_nonce_bitmap_address(&makers[i], order.nonce);
+            if let Some((window_slots, max_volume)) = test_mode.risk_limit {
+                process_and_assert_ok(
+                    &[set_risk_limit_instruction(makers[i], *token_a_mint, *token_b_mint, window_slots, max_volume)],
+                    payer,
+                    &[&makers_keypairs[i]],
+                    banks_client,
+                ).await;
+            }
+            let mut submitted_order = order.clone();
+            if let Some(tampered_amount) = test_mode.tampered_output_amount {
+                submitted_order.output_amounts[0].amount = tampered_amount;
+            }
+            let mut instruction = Instruction {
+                program_id: bebop_rfq::ID,
+                accounts: bebop_rfq::accounts::SwapSigned {
+                    config: *config,
+                    taker: *taker,
+                    maker: makers[i],
+                    receiver: *cur_receiver_address,
+                    maker_authority,
+                    taker_input_mint_token_account: *taker_token_a_account,
+                    maker_input_mint_token_account: makers_token_a_account[i],
+                    receiver_output_mint_token_account: cur_receiver_token_b_account.unwrap(),
+                    maker_output_mint_token_account: makers_token_b_account[i],
+                    fee_recipient_token_account: if test_mode.fee_bps > 0 { Some(*fee_recipient_token_b_account) } else { None },
+                    nonce_bitmap,
+                    maker_risk_state: maker_risk_state_address(&makers[i], token_a_mint, token_b_mint).0,
+                    input_mint: *token_a_mint,
+                    input_token_program: *token_a_program_id,
+                    output_mint: *token_b_mint,
+                    output_token_program: *token_b_program_id,
+                    instructions_sysvar: solana_program::sysvar::instructions::ID,
+                    payer: payer.pubkey(),
+                    system_program: system_program::ID,
+                    shared_account_owner: None,
+                    event_authority: event_authority_address(),
+                    program: bebop_rfq::ID,
+                }
+                .to_account_metas(None),
+                data: bebop_rfq::instruction::SwapSigned {
+                    order: submitted_order,
+                    event_id: 0,
+                    bumps: pda_bumps(&makers[i], token_a_mint, token_b_mint, None),
+                    shared_account: None,
+                }
+                .data(),
+            };
+            instruction
                 .accounts
-                .push(AccountMeta::new(temporary_wsol_token_accounts[1].clone(), false));
+                .iter_mut()
+                .for_each(|account| if account.pubkey == *taker { account.is_signer = true });
+            instruction.accounts.extend(transfer_hook_accounts.iter().cloned());
+            instructions.push(instruction);
+        }
+        instructions
+    }
+
+    pub async fn create_batch_swap_instructions(&self, test_mode: TestMode) -> Vec<Instruction> {
+        // token_a -> token_b swap (taker pov), all makers settled by one swap_batch instruction
+
+        let TestEnvironment {
+            transfer_hook_accounts,
+            banks_client,
+            makers,
+            taker,
+            random_receiver,
+            payer,
+            config,
+            token_a,
+            token_b,
+            token_a_mint,
+            token_a_program_id,
+            token_b_mint,
+            token_b_program_id,
+
+            taker_token_a_account,
+            makers_token_a_account,
+
+            taker_token_b_account,
+            receiver_token_b_account,
+            makers_token_b_account,
+            fee_recipient_token_b_account,
+
+            ..
+        } = self;
+
+        let (cur_receiver_address, cur_receiver_token_b_account) = match test_mode.receiver_kind {
+            ReceiverKind::AnotherAddress => (random_receiver, receiver_token_b_account),
+            _ => (taker, taker_token_b_account),
+        };
+        let mut instructions = vec![instruction::create_associated_token_account(
+            &payer.pubkey(), cur_receiver_address, token_b_mint, token_b_program_id
+        )];
+        mint_balance(test_mode.mint_amount(test_mode.input_amounts.iter().sum()), *taker_token_a_account,
+            token_a, test_mode.clone().taker_accounts.input, banks_client, payer).await;
+
+        assert_eq!(test_mode.input_amounts.len(), test_mode.output_amounts.len());
+        let mut legs = Vec::new();
+        let mut remaining_accounts = Vec::new();
+        for i in 0..test_mode.input_amounts.len() {
+            mint_balance(test_mode.mint_amount(test_mode.output_amounts[i]), makers_token_b_account.get(i).cloned(),
+                token_b, test_mode.clone().maker_accounts.output, banks_client, payer).await;
+            legs.push(BatchLeg {
+                input_amount: test_mode.input_amounts[i],
+                output_amounts: vec![AmountWithExpiry {
+                    amount: test_mode.output_amounts[i],
+                    expiry: u64::MAX,
+                }],
+                nonce: test_mode.nonce,
+                nonce_bitmap_bump: get_nonce_bitmap_bump(&makers[i], test_mode.nonce),
+                maker_risk_state_bump: maker_risk_state_address(&makers[i], token_a_mint, token_b_mint).1,
+            });
+            remaining_accounts.extend([
+                AccountMeta::new_readonly(makers[i], true),
+                AccountMeta::new(makers_token_a_account[i], false),
+                AccountMeta::new(makers_token_b_account[i], false),
+                AccountMeta::new(get_nonce_bitmap_address(&makers[i], test_mode.nonce), false),
+                AccountMeta::new(maker_risk_state_address(&makers[i], token_a_mint, token_b_mint).0, false),
+            ]);
+            if let Some((window_slots, max_volume)) = test_mode.risk_limit {
+                process_and_assert_ok(
+                    &[set_risk_limit_instruction(makers[i], *token_a_mint, *token_b_mint, window_slots, max_volume)],
+                    payer,
+                    &[&self.makers_keypairs[i]],
+                    banks_client,
+                ).await;
+            }
         }
-        instructions.push(instruction_1);
-        instructions.push(instruction_2);
+
+        let mut accounts = bebop_rfq::accounts::SwapBatch {
+            config: *config,
+            taker: *taker,
+            receiver: *cur_receiver_address,
+            taker_input_mint_token_account: *taker_token_a_account,
+            receiver_output_mint_token_account: cur_receiver_token_b_account.unwrap(),
+            fee_recipient_token_account: if test_mode.fee_bps > 0 { Some(*fee_recipient_token_b_account) } else { None },
+            input_mint: *token_a_mint,
+            input_token_program: *token_a_program_id,
+            output_mint: *token_b_mint,
+            output_token_program: *token_b_program_id,
+            payer: payer.pubkey(),
+            system_program: system_program::ID,
+            event_authority: event_authority_address(),
+            program: bebop_rfq::ID,
+        }
+        .to_account_metas(None);
+        accounts.extend(remaining_accounts);
+        accounts.extend(transfer_hook_accounts.iter().cloned());
+        instructions.push(Instruction {
+            program_id: bebop_rfq::ID,
+            accounts,
+            data: bebop_rfq::instruction::SwapBatch {
+                legs,
+                event_id: 0,
+                fee_bps: test_mode.fee_bps,
+                transfer_fee_mode: test_mode.transfer_fee_mode,
+            }
+            .data(),
+        });
         instructions
     }
 }
 
+/// Same layout as solana_sdk::ed25519_instruction, built without ed25519-dalek keypair
+pub fn new_ed25519_instruction(signer: &Keypair, message: &[u8]) -> Instruction {
+    let signature = signer.sign_message(message);
+    let public_key_offset: u16 = 2 + 14;
+    let signature_offset: u16 = public_key_offset + 32;
+    let message_data_offset: u16 = signature_offset + 64;
+
+    let mut data: Vec<u8> = vec![1, 0];
+    for offset in [
+        signature_offset, u16::MAX,
+        public_key_offset, u16::MAX,
+        message_data_offset, message.len() as u16, u16::MAX,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+    Instruction {
+        program_id: solana_program::ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum AccountKind {
     #[default]
@@ -319,6 +572,16 @@ pub enum AccountKind {
     NativeSol,
 }
 
+impl From<AccountKind> for bebop_rfq_client::AccountKind {
+    fn from(kind: AccountKind) -> Self {
+        match kind {
+            AccountKind::Token => bebop_rfq_client::AccountKind::Token,
+            AccountKind::NativeMint => bebop_rfq_client::AccountKind::NativeMint,
+            AccountKind::NativeSol => bebop_rfq_client::AccountKind::NativeSol,
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum ReceiverKind {
     #[default]
@@ -355,6 +618,30 @@ pub struct TestMode {
     pub input_mint_extensions: Option<Vec<ExtensionInitializationParams>>,
     pub output_mint_extensions: Option<Vec<ExtensionInitializationParams>>,
     pub onchain_swap_type: Option<OnchainSwapType>,
+    pub fee_bps: u16,
+    pub nonce: u64,
+    pub transfer_fee_mode: TransferFeeMode,
+    pub tampered_output_amount: Option<u64>,
+    /// Quote an expired better tier ahead of the fillable one
+    pub expired_output_tier: bool,
+    /// Shared-pda taker balance above input amount goes to the receiver
+    pub surplus_to_receiver: bool,
+    pub positive_slippage: Option<PositiveSlippage>,
+    /// Makers unwrap WSOL through `init_maker_wsol_vault` vaults
+    pub maker_wsol_vault: bool,
+    /// Native legs use Token-2022 native mint
+    pub token_2022_native_mint: bool,
+    /// Makers authorize the delegate with these limits, swaps are signed by the delegate.
+    /// Mint limits with the default mint are set on the swap input mint, then the output mint
+    pub delegate_limits: Option<DelegateLimits>,
+    /// Makers cap the pair with `(window_slots, max_volume)`
+    pub risk_limit: Option<(u64, u64)>,
+    /// Gasless taker signs `TakerOrder` instead of the transaction, `tampered_output_amount` is applied after signing
+    pub signed_taker_order: bool,
+    /// Payer is reimbursed from every fill's output
+    pub relayer_fee: Option<RelayerFee>,
+    /// Load `cargo build-sbf` shared objects instead of native processors, compute units are metered on BPF only
+    pub prefer_bpf: bool,
 }
 
 impl Default for TestMode {
@@ -376,7 +663,31 @@ impl Default for TestMode {
             expected_error: None,
             input_mint_extensions: None,
             output_mint_extensions: None,
-            onchain_swap_type: None
+            onchain_swap_type: None,
+            fee_bps: 0,
+            nonce: 0,
+            transfer_fee_mode: TransferFeeMode::Gross,
+            tampered_output_amount: None,
+            expired_output_tier: false,
+            surplus_to_receiver: false,
+            positive_slippage: None,
+            maker_wsol_vault: false,
+            token_2022_native_mint: false,
+            delegate_limits: None,
+            risk_limit: None,
+            signed_taker_order: false,
+            relayer_fee: None,
+            prefer_bpf: false,
+        }
+    }
+}
+
+impl TestMode {
+    /// In `Net` mode senders pay transfer fee on top of quoted amounts
+    pub fn mint_amount(&self, amount: u64) -> u64 {
+        match self.transfer_fee_mode {
+            TransferFeeMode::Gross => amount,
+            TransferFeeMode::Net => 2 * amount,
         }
     }
 }
@@ -408,24 +719,38 @@ macro_rules! anchor_processor {
 
 
 const TEST_AIRDROP: u64 = 5 * LAMPORTS_PER_SOL;
+const MAKER_WSOL_VAULT_LAMPORTS: u64 = 10 * LAMPORTS_PER_SOL;
 
 pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
-    let mut pt = ProgramTest::new(
-        "bebop_rfq",
-        bebop_rfq::ID,
-        anchor_processor!(bebop_rfq),
-    );
+    let mut pt = ProgramTest::default();
+    if test_mode.prefer_bpf {
+        pt.prefer_bpf(true);
+    }
+    pt.add_program("bebop_rfq", bebop_rfq::ID, anchor_processor!(bebop_rfq));
     pt.add_program("mock_swap", mock_swap::ID, anchor_processor!(mock_swap));
     pt.deactivate_feature(bpf_account_data_direct_mapping::ID);
 
-    let (banks_client, payer, _) = pt.start().await;
+    let mut context = pt.start_with_context().await;
+    let (banks_client, payer) = (context.banks_client.clone(), context.payer.insecure_clone());
+    // program is deployed upgradeable with payer as upgrade authority
+    let program_data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(payer.pubkey()),
+    }).unwrap();
+    let mut program_data_account = AccountSharedData::new(LAMPORTS_PER_SOL, program_data.len(), &bpf_loader_upgradeable::ID);
+    program_data_account.set_data_from_slice(&program_data);
+    context.set_account(&program_data_address(), &program_data_account);
+    let context = Arc::new(Mutex::new(context));
 
     let taker_keypair = Keypair::new();
     let taker = taker_keypair.pubkey();
     let random_receiver = Keypair::new().pubkey();
+    let fee_recipient = Keypair::new().pubkey();
+    let delegate_keypair = Keypair::new();
 
-    let shared_pda = Pubkey::find_program_address(
-        &[bebop_rfq::SHARED_ACCOUNT],
+    let shared_pda = shared_account_address(&taker, 0).0;
+    let config = Pubkey::find_program_address(
+        &[bebop_rfq::CONFIG],
         &bebop_rfq::ID,
     ).0;
 
@@ -460,6 +785,32 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
     )
     .await;
 
+    // Payer is the admin of the program config, protocol fees go to fee_recipient
+    process_and_assert_ok(
+        &[create_initialize_config_instruction(payer.pubkey(), payer.pubkey(), fee_recipient)],
+        &payer,
+        &[&payer],
+        &banks_client,
+    )
+    .await;
+
+    // Makers unwrap through persistent vaults, their lamports are funded by the payer
+    if test_mode.maker_wsol_vault {
+        for maker_keypair in &makers_keypairs {
+            let maker = maker_keypair.pubkey();
+            process_and_assert_ok(
+                &[
+                    init_maker_wsol_vault_instruction(maker, 0),
+                    system_instruction::transfer(&payer.pubkey(), &maker_wsol_vault_address(&maker), MAKER_WSOL_VAULT_LAMPORTS),
+                ],
+                &payer,
+                &[&payer, maker_keypair],
+                &banks_client,
+            )
+            .await;
+        }
+    }
+
     let (mut mint_a_keypair, mut mint_a, mut mint_b_keypair, mut mint_b, mint_c_keypair, mint_c) = {
         let mint_a_keypair = Keypair::new();
         let mint_a = mint_a_keypair.pubkey();
@@ -470,6 +821,21 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
         (Some(mint_a_keypair), mint_a, Some(mint_b_keypair), mint_b, Some(mint_c_keypair), mint_c)
     };
     let mut uses_temporary_wsol_token_account = false;
+    let wsol_mint = if test_mode.token_2022_native_mint {
+        // Token-2022 native mint isn't in genesis
+        if banks_client.lock().await.get_account(spl_token_2022::native_mint::ID).await.unwrap().is_none() {
+            process_and_assert_ok(
+                &[spl_token_2022::instruction::create_native_mint(&spl_token_2022::ID, &payer.pubkey()).unwrap()],
+                &payer,
+                &[&payer],
+                &banks_client,
+            )
+            .await;
+        }
+        spl_token_2022::native_mint::ID
+    } else {
+        native_mint::ID
+    };
 
     let TestMode {
         input_amounts,
@@ -482,7 +848,8 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
         expected_error,
         input_mint_extensions,
         output_mint_extensions,
-        onchain_swap_type
+        onchain_swap_type,
+        ..
     } = test_mode;
     match (&taker_accounts, &maker_accounts) {
         (
@@ -516,7 +883,7 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
             },
         ) => {
             mint_a_keypair = None;
-            mint_a = native_mint::ID;
+            mint_a = wsol_mint;
         }
         (
             Accounts {
@@ -539,7 +906,7 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
             },
         ) => {
             mint_b_keypair = None;
-            mint_b = native_mint::ID;
+            mint_b = wsol_mint;
         }
         (
             Accounts {
@@ -562,7 +929,7 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
             },
         ) => {
             mint_a_keypair = None;
-            mint_a = native_mint::ID;
+            mint_a = wsol_mint;
             uses_temporary_wsol_token_account = true;
         }
         (
@@ -586,12 +953,25 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
             },
         ) => {
             mint_b_keypair = None;
-            mint_b = native_mint::ID;
+            mint_b = wsol_mint;
             uses_temporary_wsol_token_account = true;
         }
         _ => panic!("Invalid combo"),
     };
 
+    let mut transfer_hook_accounts: Vec<AccountMeta> = Vec::new();
+    for (mint, mint_extensions) in [
+        (mint_a, &input_mint_extensions),
+        (mint_b, &output_mint_extensions),
+        (mint_c, &middle_token_info.as_ref().and_then(|x| x.mint_extensions.clone())),
+    ] {
+        if has_transfer_hook(mint_extensions) {
+            if transfer_hook_accounts.is_empty() {
+                transfer_hook_accounts.push(AccountMeta::new_readonly(mock_swap::ID, false));
+            }
+            transfer_hook_accounts.push(AccountMeta::new_readonly(get_extra_account_meta_list_address(&mint), false));
+        }
+    }
     let (token_a_program_id, token_a) = create_token(
         Arc::clone(&client), Arc::clone(&payer), &mint_a, mint_a_keypair, input_mint_extensions
     ).await;
@@ -601,6 +981,12 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
     let (token_c_program_id, token_c) = create_token(
         Arc::clone(&client), Arc::clone(&payer), &mint_c, mint_c_keypair, middle_token_info.and_then(|x| x.mint_extensions)
     ).await;
+    for meta in transfer_hook_accounts.iter().skip(1) {
+        let mint = [mint_a, mint_b, mint_c].into_iter().find(|mint| get_extra_account_meta_list_address(mint) == meta.pubkey).unwrap();
+        process_and_assert_ok(
+            &[create_initialize_extra_account_meta_list_instruction(payer.pubkey(), mint)], &payer, &[], &banks_client
+        ).await;
+    }
 
 
     let taker_token_a_account: Option<Pubkey> = get_associated_token_account(
@@ -620,6 +1006,7 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
     let receiver_token_a_account: Option<Pubkey> = get_associated_token_account(random_receiver, &token_a, taker_accounts.input.clone(), false).await;
     let receiver_token_b_account: Option<Pubkey> = get_associated_token_account(random_receiver, &token_b, taker_accounts.output.clone(), false).await;
     let receiver_token_c_account: Option<Pubkey> = get_associated_token_account(random_receiver, &token_c, AccountKind::Token, false).await;
+    let fee_recipient_token_b_account: Pubkey = get_associated_token_account(fee_recipient, &token_b, AccountKind::Token, true).await.unwrap();
     let mut makers_token_a_account: Vec<Pubkey> = Vec::new();
     let mut makers_token_b_account: Vec<Pubkey> = Vec::new();
     let mut makers_token_c_account: Vec<Pubkey> = Vec::new();
@@ -651,14 +1038,19 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
     };
 
     TestEnvironment {
+        context,
         banks_client,
         payer,
         taker_keypair,
         makers_keypairs,
+        delegate_keypair,
         makers,
         taker,
         random_receiver,
         shared_pda,
+        config,
+        fee_recipient,
+        fee_recipient_token_b_account,
 
         taker_token_a_account,
         makers_token_a_account,
@@ -683,8 +1075,8 @@ pub async fn prepare_test(test_mode: TestMode) -> TestEnvironment {
         token_c_mint: mint_c,
         token_c_program_id,
 
-        temporary_wsol_token_accounts
-        
+        temporary_wsol_token_accounts,
+        transfer_hook_accounts,
     }
 }
 
@@ -692,7 +1084,7 @@ async fn create_token(
     client: Arc<ProgramBanksClient<ProgramBanksClientProcessTransaction>>, payer: Arc<Keypair>,
     token_mint: &Pubkey, token_mint_keypair: Option<Keypair>, token_mint_extensions: Option<Vec<ExtensionInitializationParams>>
 ) -> (Pubkey, Token<ProgramBanksClientProcessTransaction>) {
-    let token_program_id = if token_mint_extensions.is_some() {
+    let token_program_id = if token_mint_extensions.is_some() || *token_mint == spl_token_2022::native_mint::ID {
         anchor_spl::token_2022::ID
     } else {
         anchor_spl::token::ID
@@ -762,7 +1154,7 @@ pub async fn mint_balance(amount: u64, wallet_token_account: Option<Pubkey>, tok
                     &payer.pubkey(),
                     &wallet_token_account.unwrap(),
                     amount + 100_000_000,
-                ), sync_native(&anchor_spl::token::ID, &wallet_token_account.unwrap()).unwrap()],
+                ), sync_native(&wsol_token_program_id(token.get_address()), &wallet_token_account.unwrap()).unwrap()],
                 &payer,
                 &[&payer],
                 &banks_client,
@@ -776,6 +1168,11 @@ pub async fn mint_balance(amount: u64, wallet_token_account: Option<Pubkey>, tok
 }
 
 
+fn wsol_token_program_id(mint: &Pubkey) -> Pubkey {
+    if *mint == spl_token_2022::native_mint::ID { spl_token_2022::ID } else { anchor_spl::token::ID }
+}
+
+
 pub async fn process_and_assert_ok(
     instructions: &[Instruction],
     payer: &Keypair,
@@ -785,6 +1182,26 @@ pub async fn process_and_assert_ok(
     let result = process_instructions(instructions, payer, signers, banks_client).await;
     assert_matches!(result, Ok(()));
 }
+
+/// Asserts the transaction failed with `expected_error`, or succeeded when there is none. True when it failed
+pub fn assert_expected_error(
+    result: std::result::Result<(), BanksClientError>,
+    expected_error: Option<&TransactionError>,
+) -> bool {
+    match expected_error {
+        Some(expected_error) => {
+            let BanksClientError::TransactionError(transaction_error) = result.unwrap_err() else {
+                panic!("The error was not a transaction error");
+            };
+            assert_eq!(&transaction_error, expected_error);
+            true
+        }
+        None => {
+            assert_matches!(result, Ok(()));
+            false
+        }
+    }
+}
 pub async fn process_instructions(
     instructions: &[Instruction],
     payer: &Keypair,
@@ -807,33 +1224,268 @@ pub async fn process_instructions(
     banks_client.process_transaction(tx).await
 }
 
-pub async fn sign_and_execute_tx(
+fn find_nonce_bitmap(maker: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[bebop_rfq::NONCE_BITMAP, maker.as_ref(), &(nonce >> 8).to_le_bytes()],
+        &bebop_rfq::ID,
+    )
+}
+
+pub fn get_nonce_bitmap_address(maker: &Pubkey, nonce: u64) -> Pubkey {
+    find_nonce_bitmap(maker, nonce).0
+}
+
+pub fn get_nonce_bitmap_bump(maker: &Pubkey, nonce: u64) -> u8 {
+    find_nonce_bitmap(maker, nonce).1
+}
+
+pub fn create_cancel_nonces_instruction(maker: Pubkey, word_pos: u64, mask: [u8; 32]) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::CancelNonces {
+            maker,
+            nonce_bitmap: Pubkey::find_program_address(
+                &[bebop_rfq::NONCE_BITMAP, maker.as_ref(), &word_pos.to_le_bytes()],
+                &bebop_rfq::ID,
+            ).0,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: bebop_rfq::instruction::CancelNonces { word_pos, mask }.data(),
+    }
+}
+
+pub fn create_cancel_taker_nonces_instruction(taker: Pubkey, word_pos: u64, mask: [u8; 32]) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::CancelTakerNonces {
+            taker,
+            nonce_bitmap: Pubkey::find_program_address(
+                &[bebop_rfq::TAKER_NONCE_BITMAP, taker.as_ref(), &word_pos.to_le_bytes()],
+                &bebop_rfq::ID,
+            ).0,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: bebop_rfq::instruction::CancelTakerNonces { word_pos, mask }.data(),
+    }
+}
+
+fn has_transfer_hook(mint_extensions: &Option<Vec<ExtensionInitializationParams>>) -> bool {
+    mint_extensions.iter().flatten().any(|extension| matches!(extension, ExtensionInitializationParams::TransferHook { .. }))
+}
+
+pub fn get_extra_account_meta_list_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[mock_swap::EXTRA_ACCOUNT_METAS, mint.as_ref()], &mock_swap::ID).0
+}
+
+pub fn create_initialize_extra_account_meta_list_instruction(payer: Pubkey, mint: Pubkey) -> Instruction {
+    Instruction {
+        program_id: mock_swap::ID,
+        accounts: mock_swap::accounts::InitializeExtraAccountMetaList {
+            payer,
+            extra_account_meta_list: get_extra_account_meta_list_address(&mint),
+            mint,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: mock_swap::instruction::InitializeExtraAccountMetaList {}.data(),
+    }
+}
+
+pub fn get_output_snapshot_address(taker: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[bebop_rfq::OUTPUT_SNAPSHOT, taker.as_ref()], &bebop_rfq::ID).0
+}
+
+pub fn create_record_output_balance_instruction(
+    taker: Pubkey, receiver: Pubkey, output_mint: Pubkey, receiver_output_mint_token_account: Option<Pubkey>
+) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::RecordOutputBalance {
+            taker,
+            receiver,
+            output_mint,
+            receiver_output_mint_token_account,
+            output_snapshot: get_output_snapshot_address(&taker),
+            instructions_sysvar: solana_program::sysvar::instructions::ID,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: bebop_rfq::instruction::RecordOutputBalance {}.data(),
+    }
+}
+
+pub fn create_assert_min_output_instruction(
+    taker: Pubkey, receiver: Pubkey, output_mint: Pubkey, receiver_output_mint_token_account: Option<Pubkey>, min_output_amount: u64
+) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::AssertMinOutput {
+            taker,
+            receiver,
+            output_mint,
+            receiver_output_mint_token_account,
+            output_snapshot: get_output_snapshot_address(&taker),
+        }.to_account_metas(None),
+        data: bebop_rfq::instruction::AssertMinOutput { min_output_amount }.data(),
+    }
+}
+
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[bebop_rfq::ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+pub fn create_initialize_config_instruction(payer: Pubkey, admin: Pubkey, fee_recipient: Pubkey) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::InitializeConfig {
+            payer,
+            program_data: program_data_address(),
+            config: Pubkey::find_program_address(&[bebop_rfq::CONFIG], &bebop_rfq::ID).0,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: bebop_rfq::instruction::InitializeConfig { admin, fee_recipient }.data(),
+    }
+}
+
+pub fn create_set_admin_instruction(config: Pubkey, admin: Pubkey, new_admin: Pubkey) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::UpdateConfig { admin, config }.to_account_metas(None),
+        data: bebop_rfq::instruction::SetAdmin { new_admin }.data(),
+    }
+}
+
+pub fn create_set_fee_recipient_instruction(config: Pubkey, admin: Pubkey, fee_recipient: Pubkey) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::UpdateConfig { admin, config }.to_account_metas(None),
+        data: bebop_rfq::instruction::SetFeeRecipient { fee_recipient }.data(),
+    }
+}
+
+pub fn create_set_paused_instruction(config: Pubkey, admin: Pubkey, paused: bool) -> Instruction {
+    Instruction {
+        program_id: bebop_rfq::ID,
+        accounts: bebop_rfq::accounts::UpdateConfig { admin, config }.to_account_metas(None),
+        data: bebop_rfq::instruction::SetPaused { paused }.data(),
+    }
+}
+
+fn sign_tx(
     instructions: &[Instruction],
     payer: &Keypair,
     taker: &Keypair,
     makers: &[Keypair],
-    banks_client: &Mutex<BanksClient>,
-) -> std::result::Result<(), BanksClientError> {
-    // assert_eq!(instructions.len(), makers.len(), "Instructions and makers length mismatch");
-
-    let mut banks_client = banks_client.lock().await;
-    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
-
+    recent_blockhash: solana_program::hash::Hash,
+) -> std::result::Result<Transaction, BanksClientError> {
     // Create the main transaction with taker and payer as signers
     let msg = Message::new_with_blockhash(instructions, Some(&payer.pubkey()), &recent_blockhash);
     let mut tx = Transaction::new_unsigned(msg);
 
     tx.message.recent_blockhash = recent_blockhash;
     let mut signatures: Vec<(Pubkey, Signature)> = vec![];
-    for signer in makers.iter().chain(std::iter::once(taker)).chain(std::iter::once(payer)) {
+    let required_signers = &tx.message.account_keys[..tx.message.header.num_required_signatures as usize];
+    // shared-pda taker doesn't sign
+    for signer in makers.iter().chain(std::iter::once(taker)).chain(std::iter::once(payer))
+        .filter(|signer| required_signers.contains(&signer.pubkey()))
+    {
         let signature = signer.try_sign_message(&tx.message_data()).unwrap();
         signatures.push((signer.pubkey(), signature));
     }
     tx.replace_signatures(&signatures)?;
+    Ok(tx)
+}
+
+pub async fn sign_and_execute_tx(
+    instructions: &[Instruction],
+    payer: &Keypair,
+    taker: &Keypair,
+    makers: &[Keypair],
+    banks_client: &Mutex<BanksClient>,
+) -> std::result::Result<(), BanksClientError> {
+    // assert_eq!(instructions.len(), makers.len(), "Instructions and makers length mismatch");
+
+    let mut banks_client = banks_client.lock().await;
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let tx = sign_tx(instructions, payer, taker, makers, recent_blockhash)?;
     // println!("tx:{:?}", tx);
-    println!("Final TX size: {}", bincode::serialize(&tx).unwrap().len());
     banks_client.process_transaction(tx).await?;
 
     Ok(())
 }
 
+/// Simulates the transaction and returns consumed compute units, tx must succeed
+pub async fn simulate_compute_units(
+    instructions: &[Instruction],
+    payer: &Keypair,
+    taker: &Keypair,
+    makers: &[Keypair],
+    banks_client: &Mutex<BanksClient>,
+) -> u64 {
+    let mut banks_client = banks_client.lock().await;
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let tx = sign_tx(instructions, payer, taker, makers, recent_blockhash).unwrap();
+    let simulation = banks_client.simulate_transaction(tx).await.unwrap();
+    assert_matches!(simulation.result, Some(Ok(())));
+    simulation.simulation_details.unwrap().units_consumed
+}
+
+/// Simulates the transaction and decodes Bebop fills from its inner instructions
+pub async fn simulate_swap_fills(
+    instructions: &[Instruction],
+    payer: &Keypair,
+    taker: &Keypair,
+    makers: &[Keypair],
+    banks_client: &Mutex<BanksClient>,
+) -> Vec<SwapFill> {
+    let mut banks_client = banks_client.lock().await;
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let tx = sign_tx(instructions, payer, taker, makers, recent_blockhash).unwrap();
+    let simulation = banks_client.simulate_transaction(tx.clone()).await.unwrap();
+    assert_matches!(simulation.result, Some(Ok(())));
+    let inner_instructions: Vec<InnerInstructions> = simulation.simulation_details.unwrap().inner_instructions
+        .expect("inner instructions are recorded")
+        .into_iter()
+        .enumerate()
+        .map(|(index, inner)| InnerInstructions {
+            index: index as u8,
+            instructions: inner.into_iter().map(|inner_instruction| inner_instruction.instruction).collect(),
+        })
+        .collect();
+    decode_swap_fills(&tx.message.account_keys, &tx.message.instructions, &inner_instructions).unwrap()
+}
+
+/// v0 transaction loading accounts from `lookup_tables`, checked against packet size
+pub async fn sign_and_execute_v0_tx(
+    instructions: &[Instruction],
+    payer: &Keypair,
+    taker: &Keypair,
+    makers: &[Keypair],
+    lookup_tables: &[AddressLookupTableAccount],
+    banks_client: &Mutex<BanksClient>,
+) -> std::result::Result<(), BanksClientError> {
+    let mut banks_client = banks_client.lock().await;
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let message = compile_v0_message(&payer.pubkey(), instructions, lookup_tables, recent_blockhash).unwrap();
+    let required_signers = message.static_account_keys()[..message.header().num_required_signatures as usize].to_vec();
+    let signers: Vec<&Keypair> = makers.iter().chain(std::iter::once(taker)).chain(std::iter::once(payer))
+        .filter(|signer| required_signers.contains(&signer.pubkey()))
+        .collect();
+    let tx = VersionedTransaction::try_new(message, &signers).unwrap();
+    let tx_size = bincode::serialize(&tx).unwrap().len();
+    assert!(tx_size <= PACKET_DATA_SIZE, "v0 tx size {} > {}", tx_size, PACKET_DATA_SIZE);
+    banks_client.process_transaction(tx).await
+}
+
+/// Bebop lookup table owned by payer with `addresses`, warps a slot so they are usable
+pub async fn create_test_lookup_table(env: &TestEnvironment, addresses: &[Pubkey]) -> AddressLookupTableAccount {
+    let recent_slot = env.banks_client.lock().await.get_sysvar::<SlotHashes>().await.unwrap().first().unwrap().0;
+    let (create_instruction, lookup_table) = create_bebop_lookup_table(env.payer.pubkey(), env.payer.pubkey(), recent_slot);
+    process_and_assert_ok(&[create_instruction], &env.payer, &[], &env.banks_client).await;
+    for extend_instruction in extend_bebop_lookup_table(lookup_table, env.payer.pubkey(), env.payer.pubkey(), addresses) {
+        process_and_assert_ok(&[extend_instruction], &env.payer, &[], &env.banks_client).await;
+    }
+    let current_slot = env.banks_client.lock().await.get_sysvar::<Clock>().await.unwrap().slot;
+    env.context.lock().await.warp_to_slot(current_slot + 1).unwrap();
+    let data = env.banks_client.lock().await.get_account(lookup_table).await.unwrap().unwrap().data;
+    lookup_table_account(lookup_table, &data).unwrap()
+}
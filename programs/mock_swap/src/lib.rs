@@ -1,9 +1,12 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::hash::hash};
 use anchor_spl::{token::Token, token_2022::Token2022, token_interface::{Mint, TokenAccount, TokenInterface}};
 mod utils;
 
 
 pub const POOL_ACCOUNT: &[u8] = b"pool-account";
+pub const EXTRA_ACCOUNT_METAS: &[u8] = b"extra-account-metas";
+/// spl-transfer-hook-interface Execute discriminator is sha256 of this, first 8 bytes
+const EXECUTE_DISCRIMINATOR_INPUT: &[u8] = b"spl-transfer-hook-interface:execute";
 
 declare_id!("mock111111111111111111111111111111111111111");
 
@@ -128,9 +131,45 @@ pub mod mock_swap {
         )?;
        Ok(())
     }
+
+    /// Mock transfer-hook: ExtraAccountMetaList for `mint` with no extra accounts
+    pub fn initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
+        let mut data = ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?;
+        // TLV entry: Execute discriminator, length, empty PodSlice
+        data[..8].copy_from_slice(&hash(EXECUTE_DISCRIMINATOR_INPUT).to_bytes()[..8]);
+        data[8..12].copy_from_slice(&4u32.to_le_bytes());
+        data[12..16].copy_from_slice(&0u32.to_le_bytes());
+        Ok(())
+    }
+
+    /// Mock transfer-hook Execute, accepts every transfer
+    pub fn fallback<'info>(_program_id: &Pubkey, _accounts: &'info [AccountInfo<'info>], data: &[u8]) -> Result<()> {
+        require!(
+            data.starts_with(&hash(EXECUTE_DISCRIMINATOR_INPUT).to_bytes()[..8]),
+            anchor_lang::error::ErrorCode::InstructionFallbackNotFound
+        );
+        Ok(())
+    }
 }
 
 
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: written manually, 16 bytes TLV with no extra accounts
+    #[account(
+        init,
+        payer = payer,
+        space = 16,
+        seeds = [EXTRA_ACCOUNT_METAS, mint.key().as_ref()],
+        bump,
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct MockRaydiumCPMM<'info> {
     pub payer: Signer<'info>,